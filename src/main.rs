@@ -1,11 +1,18 @@
-use clap::{Parser, Subcommand};
-use comfy_table::Table;
+use clap::{Parser, Subcommand, ValueEnum};
+use comfy_table::{Cell, Color, Table};
+use futures::future::LocalBoxFuture;
+use futures::stream::{self, StreamExt};
+use lopdf::{Document, Object, ObjectId};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{BTreeSet, HashMap};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::path::PathBuf;
-use chrono::{Utc, TimeZone};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::OnceLock;
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc};
 use regex::Regex;
 use std::collections::HashSet;
 use strsim::normalized_levenshtein;
@@ -23,6 +30,8 @@ struct Args {
     pubs: Option<String>,
     #[arg(short, long)]
     list: bool,
+    #[arg(long, global = true, help = "Serve charts, METAR/TAF and routes from the local cache only")]
+    offline: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -41,6 +50,8 @@ enum Commands {
         raw: bool,
         #[arg(long)]
         json: bool,
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..), help = "Show every observation from the last N hours with a trend summary")]
+        hours: Option<u32>,
     },
     Taf {
         station: String,
@@ -56,20 +67,325 @@ enum Commands {
         #[arg(long)]
         json: bool,
     },
+    /// Flight-category board for every airport in a facility, worst first
+    Wxboard {
+        #[arg(long, help = "Facility group to show (default: ZDC)")]
+        artcc: Option<String>,
+        #[arg(long, help = "Show a named group from config instead")]
+        group: Option<String>,
+        #[arg(long)]
+        json: bool,
+    },
+    #[command(
+        visible_alias = "charts",
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
     Chart {
-        airport: String,
+        #[command(subcommand)]
+        action: Option<ChartCommand>,
+        #[arg(required = true)]
+        airport: Option<String>,
         #[arg(help = "Search terms (procedure name, approach, runway, etc.)")]
         query: Vec<String>,
         #[arg(short = 'l', long = "link", help = "Print PDF URL only")]
         link: bool,
-        #[arg(long, help = "Airac cycle (optional)")]
-        airac: Option<i32>,
+        #[arg(
+            long,
+            value_parser = parse_airac_arg,
+            help = "AIRAC cycle: current, next, or YYNN (e.g. 2511); aviationapi only serves the current one"
+        )]
+        airac: Option<AiracCycle>,
+        #[arg(long, help = "Ignore cached chart listings/PDFs and refetch")]
+        refresh: bool,
+        #[arg(short = 'm', long, help = "Merge continuation pages into one PDF before opening")]
+        merge: bool,
+        #[arg(
+            short = 'r',
+            long,
+            value_enum,
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "auto",
+            help = "Draw the chart in the terminal instead of opening it (auto, kitty, iterm, sixel, text)"
+        )]
+        render: Option<RenderMode>,
+    },
+    /// Decode raw METARs/TAFs from the argument or stdin, offline
+    Decode {
+        #[arg(help = "Report text (default: read stdin)")]
+        text: Vec<String>,
+        #[arg(long, help = "Print the decoded fields as JSON")]
+        json: bool,
+    },
+    /// SID/STAR transitions, fixes and restrictions from FAA CIFP data
+    #[command(visible_alias = "proc")]
+    Procedure {
+        airport: String,
+        #[arg(help = "SID/STAR name or code (e.g. JCOBY4, \"CAVLR FOUR\"); omit to list all")]
+        name: Vec<String>,
+        #[arg(short = 't', long, help = "Only show this transition (e.g. AML, RW19C)")]
+        transition: Option<String>,
+        #[command(flatten)]
+        cifp: CifpArg,
+    },
+    /// Head/crosswind per runway from the current METAR, with the favored runway
+    Winds {
+        airport: String,
+        #[command(flatten)]
+        cifp: CifpArg,
+    },
+    /// Recommended arrival/departure runways from the current wind and flows.toml rules
+    Config {
+        airport: String,
+        #[command(flatten)]
+        cifp: CifpArg,
+    },
+    /// ATIS broadcast text from the current METAR; the letter advances with each new report
+    Atis {
+        airport: String,
+        #[arg(short = 'l', long, value_parser = parse_atis_letter, help = "Set the information letter (e.g. B or BRAVO)")]
+        letter: Option<char>,
+        #[command(flatten)]
+        cifp: CifpArg,
+    },
+    Airac {
+        #[arg(value_parser = parse_airac_arg, help = "Cycle to show: current, next, or YYNN (default: current)")]
+        cycle: Option<AiracCycle>,
+        #[arg(short = 'n', long, default_value_t = 3, help = "Number of upcoming cycles to list")]
+        count: usize,
+    },
+}
+
+/// `--cifp` for the commands that read FAA CIFP data (procedures, runway headings, magnetic variation).
+#[derive(clap::Args)]
+struct CifpArg {
+    #[arg(long, help = "Path to the FAACIFP18 file (default: $ZDC_CIFP, [settings] cifp, or <cache>/cifp/FAACIFP18)")]
+    cifp: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum ChartCommand {
+    /// Download every chart PDF for a set of airports into the local cache
+    Sync {
+        #[arg(help = "Airports to sync (default: the --artcc/--group list)")]
+        airports: Vec<String>,
+        #[arg(long, help = "Sync every airport in this facility's group (e.g. ZDC)")]
+        artcc: Option<String>,
+        #[arg(long, help = "Sync every airport in a named group from config")]
+        group: Option<String>,
+        #[arg(
+            long,
+            value_parser = parse_airac_arg,
+            help = "AIRAC cycle: current, next, or YYNN (default: current)"
+        )]
+        airac: Option<AiracCycle>,
+        #[arg(short = 'j', long, default_value_t = 6, help = "Parallel downloads")]
+        jobs: usize,
+    },
+    /// Search for a chart across several airports at once
+    Search {
+        #[arg(required = true, help = "Search terms (procedure name, approach, runway, etc.)")]
+        query: Vec<String>,
+        #[arg(short = 'a', long, value_delimiter = ',', help = "Airports to search, e.g. IAD,DCA,BWI")]
+        airports: Vec<String>,
+        #[arg(long, help = "Search every airport in this facility's group (e.g. ZDC)")]
+        artcc: Option<String>,
+        #[arg(long, help = "Search every airport in a named group from config")]
+        group: Option<String>,
+        #[arg(long, value_parser = parse_airac_arg, help = "AIRAC cycle: current, next, or YYNN")]
+        airac: Option<AiracCycle>,
+        #[arg(short = 'n', long, default_value_t = 15, help = "Maximum results to show")]
+        limit: usize,
+    },
+    /// List every chart for an airport, grouped by type
+    List {
+        airport: String,
+        #[arg(short = 't', long = "type", value_enum, value_delimiter = ',', help = "Only these chart types")]
+        types: Vec<ChartType>,
+        #[arg(long, value_parser = parse_airac_arg, help = "AIRAC cycle: current, next, or YYNN")]
+        airac: Option<AiracCycle>,
+        #[arg(long, help = "Only charts added, changed or deleted this cycle (needs a source with amendment data, e.g. dtpp)")]
+        changed: bool,
+        #[arg(long, conflicts_with = "csv")]
+        json: bool,
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Print a chart's text layer (altitudes, speeds, transitions, notes) to stdout
+    Text {
+        airport: String,
+        #[arg(required = true, help = "Search terms (procedure name, approach, runway, etc.)")]
+        query: Vec<String>,
+        #[arg(long, value_parser = parse_airac_arg, help = "AIRAC cycle: current, next, or YYNN")]
+        airac: Option<AiracCycle>,
+        #[arg(long, help = "Ignore cached chart listings/PDFs and refetch")]
+        refresh: bool,
+    },
+    /// Save a chart under a short alias (`zdc chart <ALIAS>` opens it)
+    Pin {
+        airport: String,
+        #[arg(required = true, help = "Search terms for the chart to pin")]
+        query: Vec<String>,
+        #[arg(long = "as", help = "Alias to pin it under (default: <airport>-<query>)")]
+        alias: Option<String>,
+        #[arg(long, value_parser = parse_airac_arg, help = "AIRAC cycle: current, next, or YYNN")]
+        airac: Option<AiracCycle>,
+    },
+    /// Remove a pinned chart
+    Unpin { alias: String },
+    /// Show pinned and recently opened charts
+    Recent {
+        #[arg(short = 'n', long, default_value_t = 10, help = "Recent entries to show")]
+        limit: usize,
+    },
+    /// Compare the chart lists of two AIRAC cycles
+    Diff {
+        #[arg(help = "Airport or airport group (e.g. IAD or ZDC)")]
+        target: String,
+        #[arg(long, value_parser = parse_airac_arg, help = "Base cycle (default: current)")]
+        from: Option<AiracCycle>,
+        #[arg(long, value_parser = parse_airac_arg, help = "New cycle (default: next)")]
+        to: Option<AiracCycle>,
+        #[arg(long, help = "Compare names and codes only; skip downloading PDFs to hash them")]
+        names_only: bool,
+        #[arg(short = 'j', long, default_value_t = 6, help = "Parallel downloads")]
+        jobs: usize,
     },
 }
 
 #[derive(Deserialize, Serialize)]
 struct Config {
     pubs: HashMap<String, String>,
+    #[serde(default)]
+    settings: Settings,
+    /// Named airport lists, e.g. `zdc = ["IAD", "DCA", ...]`.
+    #[serde(default)]
+    groups: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    charts: ChartsConfig,
+    /// Per-airport ATIS text: name, approaches, runways, NOTAMs.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    atis: HashMap<String, AtisConfig>,
+}
+
+/// `[atis.IAD]` section.
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct AtisConfig {
+    /// Spoken airport name, e.g. "Washington Dulles".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// Approach line, e.g. "ILS or RNAV approaches in use".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    approaches: Option<String>,
+    /// Runways in use; when empty, `zdc config`'s recommendation is used.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    arrivals: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    departures: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notams: Vec<String>,
+    /// Closing lines before "advise on initial contact".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    remarks: Vec<String>,
+}
+
+/// How `chart --render` draws a PDF in the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum RenderMode {
+    /// Pick from ZDC_GRAPHICS or the terminal's environment.
+    Auto,
+    Kitty,
+    Iterm,
+    Sixel,
+    /// Print the PDF's text layer.
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ChartProviderKind {
+    #[default]
+    Aviationapi,
+    Dtpp,
+    Local,
+}
+
+/// `[charts]` section: which backend answers chart lookups.
+#[derive(Default, Deserialize, Serialize)]
+struct ChartsConfig {
+    #[serde(default)]
+    provider: ChartProviderKind,
+    /// aviationapi base URL (ZDC_CHARTS_BASE still wins).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_url: Option<String>,
+    /// d-TPP metafile URL; `{cycle}` is replaced with the AIRAC ident.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dtpp_metafile: Option<String>,
+    /// Directory of PDFs laid out as `<dir>/<AIRPORT>/*.pdf` (optionally under `<dir>/<YYNN>/`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_dir: Option<PathBuf>,
+}
+
+/// Built-in airport lists used when a group is not defined in config.
+fn default_group(name: &str) -> Option<Vec<String>> {
+    let list: &[&str] = match name.to_lowercase().as_str() {
+        "zdc" => &[
+            "IAD", "DCA", "BWI", "RIC", "ORF", "RDU", "ADW", "CHO", "ROA", "PHF", "GSO", "SBY",
+        ],
+        _ => return None,
+    };
+    Some(list.iter().map(|s| s.to_string()).collect())
+}
+
+/// Explicit airports plus the members of any named groups, exiting on an unknown group.
+fn airport_list(
+    cfg: &Config,
+    cfg_path: &std::path::Path,
+    airports: &[String],
+    groups: [&Option<String>; 2],
+) -> Vec<String> {
+    let mut list: Vec<String> = airports
+        .iter()
+        .flat_map(|a| a.split(','))
+        .map(|a| a.trim().to_uppercase())
+        .filter(|a| !a.is_empty())
+        .collect();
+    for name in groups.into_iter().flatten() {
+        match group_airports(cfg, name) {
+            Some(g) => list.extend(g),
+            None => {
+                eprintln!("Unknown airport group '{}'. Define it under [groups] in {:?}.", name, cfg_path);
+                std::process::exit(2);
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    list.retain(|a| seen.insert(a.clone()));
+    list
+}
+
+fn group_airports(cfg: &Config, name: &str) -> Option<Vec<String>> {
+    let key = normalize_alias(name);
+    cfg.groups
+        .iter()
+        .find(|(k, _)| normalize_alias(k) == key)
+        .map(|(_, v)| v.iter().map(|a| a.trim().to_uppercase()).collect())
+        .or_else(|| default_group(name))
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct Settings {
+    /// Never touch the network; answer everything from the local cache.
+    #[serde(default)]
+    offline: bool,
+    /// Draw charts in the terminal instead of opening a PDF viewer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    render: Option<RenderMode>,
+    /// FAA CIFP (ARINC 424) file used by `procedure`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cifp: Option<PathBuf>,
 }
 
 fn config_path() -> PathBuf {
@@ -88,16 +404,160 @@ fn config_path() -> PathBuf {
     PathBuf::from("pubs.toml")
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Length of one AIRAC cycle in days.
+const AIRAC_CYCLE_DAYS: i64 = 28;
+
+/// Effective date of cycle 2001, used as the anchor for the 28-day schedule.
+fn airac_epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2020, 1, 2).unwrap()
+}
+
+/// An AIRAC cycle, identified by its effective date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct AiracCycle {
+    effective: NaiveDate,
+}
+
+impl AiracCycle {
+    /// The cycle in effect on `date`.
+    fn containing(date: NaiveDate) -> Self {
+        let days = (date - airac_epoch()).num_days();
+        let n = days.div_euclid(AIRAC_CYCLE_DAYS);
+        AiracCycle {
+            effective: airac_epoch() + Duration::days(n * AIRAC_CYCLE_DAYS),
+        }
+    }
+
+    fn current() -> Self {
+        Self::containing(Utc::now().date_naive())
+    }
+
+    fn next(&self) -> Self {
+        AiracCycle {
+            effective: self.effective + Duration::days(AIRAC_CYCLE_DAYS),
+        }
+    }
+
+    fn previous(&self) -> Self {
+        AiracCycle {
+            effective: self.effective - Duration::days(AIRAC_CYCLE_DAYS),
+        }
+    }
+
+    /// Parse a `YYNN` identifier, rejecting cycle numbers that do not exist in that year.
+    fn from_ident(ident: &str) -> Result<Self, String> {
+        let s = ident.trim();
+        if s.len() != 4 || !s.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("invalid AIRAC cycle '{}': expected YYNN", ident));
+        }
+        let yy: i32 = s[..2].parse().unwrap();
+        let nn: i64 = s[2..].parse().unwrap();
+        let year = 2000 + yy;
+        let jan1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        let mut first = Self::containing(jan1);
+        if first.effective < jan1 {
+            first = first.next();
+        }
+        if nn < 1 {
+            return Err(format!("invalid AIRAC cycle '{}': cycle number starts at 01", ident));
+        }
+        let cycle = AiracCycle {
+            effective: first.effective + Duration::days((nn - 1) * AIRAC_CYCLE_DAYS),
+        };
+        if cycle.effective.year() != year {
+            return Err(format!(
+                "invalid AIRAC cycle '{}': 20{} has only {} cycles",
+                ident,
+                &s[..2],
+                first.cycles_in_year()
+            ));
+        }
+        Ok(cycle)
+    }
+
+    fn cycles_in_year(&self) -> i64 {
+        let year = self.effective.year();
+        let mut c = Self::containing(NaiveDate::from_ymd_opt(year, 1, 1).unwrap());
+        if c.effective.year() != year {
+            c = c.next();
+        }
+        let mut n = 0;
+        while c.effective.year() == year {
+            n += 1;
+            c = c.next();
+        }
+        n
+    }
+
+    /// Cycle identifier in `YYNN` form.
+    fn ident(&self) -> String {
+        let number = self.effective.ordinal0() as i64 / AIRAC_CYCLE_DAYS + 1;
+        format!("{:02}{:02}", self.effective.year() % 100, number)
+    }
+
+    /// Last day the cycle is in effect.
+    fn expires(&self) -> NaiveDate {
+        self.next().effective - Duration::days(1)
+    }
+
+    /// Days from `today` until the next cycle becomes effective.
+    fn days_remaining(&self, today: NaiveDate) -> i64 {
+        (self.next().effective - today).num_days()
+    }
+}
+
+impl std::fmt::Display for AiracCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ident())
+    }
+}
+
+fn parse_airac_arg(s: &str) -> Result<AiracCycle, String> {
+    match s.trim().to_lowercase().as_str() {
+        "current" | "cur" => Ok(AiracCycle::current()),
+        "next" => Ok(AiracCycle::current().next()),
+        other => AiracCycle::from_ident(other),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum ChartType {
+    Apd,
     Sid,
     Star,
     Iap,
-    Apd,
+    Gen,
+    #[value(skip)]
     Unknown,
 }
 
+impl std::fmt::Display for ChartType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ChartType::Apd => "APD",
+            ChartType::Sid => "SID",
+            ChartType::Star => "STAR",
+            ChartType::Iap => "IAP",
+            ChartType::Gen => "GEN",
+            ChartType::Unknown => "OTHER",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 impl ChartType {
+    /// Chart code used in listings for this type.
+    fn code(&self) -> &'static str {
+        match self {
+            ChartType::Apd => "APD",
+            ChartType::Sid => "DP",
+            ChartType::Star => "STAR",
+            ChartType::Iap => "IAP",
+            ChartType::Gen => "GEN",
+            ChartType::Unknown => "",
+        }
+    }
+
     fn infer(name: &str) -> Self {
         let up = name.to_uppercase();
         if up.contains("ILS")
@@ -143,28 +603,105 @@ impl ChartQuery {
     }
 }
 
-fn airport_name_map(code: &str) -> Option<&'static str> {
-    match code.to_uppercase().as_str() {
-        "IAD" => Some("DULLES"),
-        "DCA" => Some("WASHINGTON"),
-        "BWI" => Some("BALTIMORE"),
-        "RIC" => Some("RICHMOND"),
-        "ORF" => Some("NORFOLK"),
-        "RDU" => Some("RALEIGH"),
-        "OAK" => Some("OAKLAND"),
-        _ => None,
+/// One `aliases.toml` as written; `AliasTable::merge` folds it in with normalized keys.
+#[derive(Default, Deserialize)]
+struct AliasFile {
+    /// Airport code -> word used in chart titles, e.g. `IAD = "DULLES"`.
+    #[serde(default)]
+    names: HashMap<String, String>,
+    #[serde(default)]
+    global: HashMap<String, String>,
+    #[serde(default)]
+    airports: HashMap<String, HashMap<String, String>>,
+}
+
+/// Chart-query aliases with normalized keys.
+#[derive(Default)]
+struct AliasTable {
+    names: HashMap<String, String>,
+    global: HashMap<String, String>,
+    airports: HashMap<String, HashMap<String, String>>,
+}
+
+impl AliasTable {
+    fn merge(&mut self, file: AliasFile) {
+        for (k, v) in file.names {
+            self.names.insert(norm_airport_for_routes(&k), v.to_uppercase());
+        }
+        for (k, v) in file.global {
+            self.global.insert(alias_key(&k), v.to_uppercase());
+        }
+        for (ap, map) in file.airports {
+            let entry = self.airports.entry(norm_airport_for_routes(&ap)).or_default();
+            for (k, v) in map {
+                entry.insert(alias_key(&k), v.to_uppercase());
+            }
+        }
+    }
+
+    /// Official title for `query`, checking the airport's aliases before the global ones.
+    fn lookup(&self, airport: Option<&str>, query: &str) -> Option<&str> {
+        let key = alias_key(query);
+        airport
+            .and_then(|ap| self.airports.get(&norm_airport_for_routes(ap)))
+            .and_then(|m| m.get(&key))
+            .or_else(|| self.global.get(&key))
+            .map(|s| s.as_str())
+    }
+
+    fn airport_name(&self, code: &str) -> Option<&str> {
+        self.names.get(&norm_airport_for_routes(code)).map(|s| s.as_str())
+    }
+}
+
+fn alias_key(s: &str) -> String {
+    let lower = s.trim().to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let words = match words.split_first() {
+        Some((&"the", rest)) if !rest.is_empty() => rest,
+        _ => &words[..],
+    };
+    words.join(" ")
+}
+
+fn aliases_path() -> PathBuf {
+    if let Ok(p) = std::env::var("ZDC_ALIASES") {
+        return PathBuf::from(p);
     }
+    config_path()
+        .parent()
+        .map(|d| d.join("aliases.toml"))
+        .unwrap_or_else(|| PathBuf::from("aliases.toml"))
+}
+
+/// Shipped aliases overlaid with the user's file, loaded once per run.
+fn alias_table() -> &'static AliasTable {
+    static TABLE: OnceLock<AliasTable> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = AliasTable::default();
+        let shipped: AliasFile =
+            toml::from_str(include_str!("aliases.toml")).expect("built-in aliases.toml is valid");
+        table.merge(shipped);
+        let path = aliases_path();
+        if let Ok(s) = fs::read_to_string(&path) {
+            match toml::from_str::<AliasFile>(&s) {
+                Ok(user) => table.merge(user),
+                Err(e) => eprintln!("ignoring {:?}: {}", path, e),
+            }
+        }
+        table
+    })
 }
 
 fn normalize_chart_name(name: &str, airport: Option<&str>) -> String {
     let s = name.trim().to_uppercase();
-    // simple aliases
-    if s == "TAXI" {
-        return "AIRPORT DIAGRAM".to_string();
+    let aliases = alias_table();
+    if let Some(title) = aliases.lookup(airport, name) {
+        return title.to_string();
     }
     // match pattern like CNDEL5 or RNO1
-    let re = Regex::new(r"^([A-Z]+)(\d)$").unwrap();
-    if let Some(caps) = re.captures(&s) {
+    static NUMBERED: OnceLock<Regex> = OnceLock::new();
+    if let Some(caps) = metar_regex(&NUMBERED, r"^([A-Z]+)(\d)$").captures(&s) {
         let mut base = caps.get(1).unwrap().as_str().to_string();
         let digit = caps.get(2).unwrap().as_str();
         let word = match digit {
@@ -179,34 +716,99 @@ fn normalize_chart_name(name: &str, airport: Option<&str>) -> String {
             "9" => "NINE",
             d => d,
         };
-        if let Some(ap) = airport {
-            if base == ap.to_uppercase() {
-                if let Some(exp) = airport_name_map(ap) {
-                    base = exp.to_string();
-                }
-            }
+        if let Some(ap) = airport
+            && base == ap.to_uppercase()
+            && let Some(exp) = aliases.airport_name(ap)
+        {
+            base = exp.to_string();
         }
         return format!("{base} {word}");
     }
     s
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 struct ChartInfo {
     chart_name: String,
     chart_code: String,
     pdf_path: String,
+    #[serde(rename = "faa_ident")]
     _faa_ident: String,
+    #[serde(rename = "icao_ident")]
     _icao_ident: String,
+    /// Amendment number (d-TPP `amdtnum`), e.g. `4A`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    amendment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    amendment_date: Option<String>,
+    /// What the current cycle did to this chart, when the source says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_action: Option<UserAction>,
+}
+
+/// d-TPP `useraction` flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum UserAction {
+    Added,
+    Changed,
+    Deleted,
+}
+
+impl UserAction {
+    fn from_flag(flag: &str) -> Option<Self> {
+        match flag.trim().to_uppercase().as_str() {
+            "A" => Some(UserAction::Added),
+            "C" => Some(UserAction::Changed),
+            "D" => Some(UserAction::Deleted),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for UserAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            UserAction::Added => "added",
+            UserAction::Changed => "changed",
+            UserAction::Deleted => "deleted",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 impl ChartInfo {
+    /// d-TPP keeps a chart's record for the cycle that deletes it; there is no PDF to open.
+    fn is_deleted(&self) -> bool {
+        self.user_action == Some(UserAction::Deleted)
+    }
+
+    /// e.g. `Amdt 4A (10/01/26), changed this cycle`, when the source has amendment data.
+    fn amendment_note(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(a) = &self.amendment {
+            match &self.amendment_date {
+                Some(d) => parts.push(format!("Amdt {} ({})", a, d)),
+                None => parts.push(format!("Amdt {}", a)),
+            }
+        }
+        if let Some(u) = self.user_action {
+            parts.push(format!("{} this cycle", u));
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(", "))
+        }
+    }
+
     fn chart_type(&self) -> ChartType {
         match self.chart_code.to_uppercase().as_str() {
-            "DP" => ChartType::Sid,
+            "DP" | "ODP" => ChartType::Sid,
             "STAR" => ChartType::Star,
             "IAP" => ChartType::Iap,
             "APD" => ChartType::Apd,
+            "GEN" | "MIN" | "HOT" | "LAH" => ChartType::Gen,
             _ => ChartType::Unknown,
         }
     }
@@ -287,6 +889,7 @@ async fn fetch_charts_from_api(
                         pdf_path,
                         _faa_ident: faa,
                         _icao_ident: icao,
+                        ..Default::default()
                     });
                 }
             }
@@ -311,6 +914,7 @@ async fn fetch_charts_from_api(
                     pdf_path,
                     _faa_ident: faa,
                     _icao_ident: icao,
+                    ..Default::default()
                 });
             }
         }
@@ -333,6 +937,7 @@ async fn fetch_charts_from_api(
                             pdf_path,
                             _faa_ident: faa,
                             _icao_ident: icao,
+                            ..Default::default()
                         });
                     }
                 }
@@ -344,6 +949,347 @@ async fn fetch_charts_from_api(
     Ok(out)
 }
 
+/// A source of chart listings for an airport.
+trait ChartProvider {
+    /// Short identifier, also recorded in the chart cache.
+    fn name(&self) -> &'static str;
+
+    /// Human-readable description for verbose output.
+    fn describe(&self) -> String {
+        self.name().to_string()
+    }
+
+    /// Charts for `airport`; `cycle` is `None` for whatever the source currently publishes.
+    fn fetch_charts<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        airport: &'a str,
+        cycle: Option<&'a AiracCycle>,
+        verbose: bool,
+    ) -> LocalBoxFuture<'a, Result<Vec<ChartInfo>, Box<dyn std::error::Error>>>;
+
+    /// Absolute URL of a chart's PDF.
+    fn pdf_url(&self, chart: &ChartInfo) -> String {
+        chart.pdf_path.clone()
+    }
+}
+
+/// aviationapi.com v2 charts endpoint.
+struct AviationApiProvider {
+    base: String,
+}
+
+impl ChartProvider for AviationApiProvider {
+    fn name(&self) -> &'static str {
+        "aviationapi"
+    }
+
+    fn describe(&self) -> String {
+        format!("aviationapi ({})", self.base)
+    }
+
+    fn fetch_charts<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        airport: &'a str,
+        cycle: Option<&'a AiracCycle>,
+        verbose: bool,
+    ) -> LocalBoxFuture<'a, Result<Vec<ChartInfo>, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            // the charts endpoint has no cycle parameter: it only serves the cycle in effect
+            if let Some(c) = cycle {
+                return Err(format!(
+                    "aviationapi only serves the current AIRAC cycle ({}); set [charts] provider = \"dtpp\" to look at AIRAC {}",
+                    AiracCycle::current(),
+                    c
+                )
+                .into());
+            }
+            let mut charts = fetch_charts_from_api(client, &self.base, airport).await?;
+            if charts.is_empty() && airport.len() == 3 && !airport.starts_with('K') {
+                let k_air = format!("K{}", airport.to_uppercase());
+                if verbose {
+                    eprintln!("retry GET {}/charts?airport={}", self.base, k_air);
+                }
+                charts = fetch_charts_from_api(client, &self.base, &k_air).await?;
+            }
+            Ok(charts)
+        })
+    }
+
+    fn pdf_url(&self, chart: &ChartInfo) -> String {
+        absolute_pdf_url(&self.base, &chart.pdf_path)
+    }
+}
+
+const DEFAULT_DTPP_METAFILE: &str = "https://aeronav.faa.gov/d-tpp/{cycle}/xml_data/d-TPP_Metafile.xml";
+
+/// FAA digital Terminal Procedures Publication, read from its XML metafile.
+struct DtppProvider {
+    metafile: String,
+    /// Metafiles already parsed in this run, by cycle ident; a sync looks up many airports in one file.
+    parsed: futures::lock::Mutex<HashMap<String, Rc<DtppMetafile>>>,
+}
+
+impl DtppProvider {
+    /// The metafile for `cycle`, downloaded once per cycle into the cache.
+    async fn metafile(
+        &self,
+        client: &reqwest::Client,
+        cycle: &AiracCycle,
+        verbose: bool,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let path = cache_root()
+            .join("dtpp")
+            .join(cycle.ident())
+            .join("d-TPP_Metafile.xml");
+        if let Ok(s) = fs::read_to_string(&path) {
+            return Ok(s);
+        }
+        let url = self.metafile.replace("{cycle}", &cycle.ident());
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            let local = url.strip_prefix("file://").unwrap_or(&url);
+            if verbose {
+                eprintln!("reading d-TPP metafile {}", local);
+            }
+            return fs::read_to_string(local)
+                .map_err(|e| format!("failed to read d-TPP metafile {}: {}", local, e).into());
+        }
+        if verbose {
+            eprintln!("GET {}", url);
+        }
+        let resp = client
+            .get(&url)
+            .header("User-Agent", "ZDC-Chart-CLI/1.0")
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(format!("d-TPP metafile {}: {}", status, url).into());
+        }
+        let body = resp.text().await?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // same write-then-rename as the chart cache; a truncated metafile would be served forever
+        let tmp = path.with_extension("xml.part");
+        fs::write(&tmp, &body)?;
+        fs::rename(&tmp, &path)?;
+        Ok(body)
+    }
+
+    /// The metafile for `cycle`, parsed on first use and shared by every later lookup.
+    async fn parsed_metafile(
+        &self,
+        client: &reqwest::Client,
+        cycle: &AiracCycle,
+        verbose: bool,
+    ) -> Result<Rc<DtppMetafile>, Box<dyn std::error::Error>> {
+        // held across the download so concurrent lookups wait for one parse instead of racing
+        let mut parsed = self.parsed.lock().await;
+        if let Some(meta) = parsed.get(&cycle.ident()) {
+            return Ok(meta.clone());
+        }
+        let xml = self.metafile(client, cycle, verbose).await?;
+        let pdf_base = format!("https://aeronav.faa.gov/d-tpp/{}", cycle.ident());
+        let meta = Rc::new(parse_dtpp_metafile(&xml, &pdf_base)?);
+        if let Some(c) = &meta.cycle
+            && *c != cycle.ident()
+        {
+            eprintln!("warning: d-TPP metafile is for cycle {}, expected {}", c, cycle);
+        }
+        parsed.insert(cycle.ident(), meta.clone());
+        Ok(meta)
+    }
+}
+
+impl ChartProvider for DtppProvider {
+    fn name(&self) -> &'static str {
+        "dtpp"
+    }
+
+    fn describe(&self) -> String {
+        format!("FAA d-TPP ({})", self.metafile)
+    }
+
+    fn fetch_charts<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        airport: &'a str,
+        cycle: Option<&'a AiracCycle>,
+        verbose: bool,
+    ) -> LocalBoxFuture<'a, Result<Vec<ChartInfo>, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let cycle = cycle.copied().unwrap_or_else(AiracCycle::current);
+            let meta = self.parsed_metafile(client, &cycle, verbose).await?;
+            Ok(meta.charts(airport))
+        })
+    }
+}
+
+/// A parsed d-TPP metafile: every airport's records, keyed by FAA identifier.
+struct DtppMetafile {
+    /// `cycle` attribute of the root element.
+    cycle: Option<String>,
+    airports: HashMap<String, Vec<ChartInfo>>,
+    /// ICAO identifier -> FAA identifier, for airports whose ICAO id isn't just `K` + FAA.
+    icao: HashMap<String, String>,
+}
+
+impl DtppMetafile {
+    /// Records for `airport` (FAA or ICAO identifier), deleted ones included.
+    fn charts(&self, airport: &str) -> Vec<ChartInfo> {
+        let want = airport.trim().to_uppercase();
+        self.airports
+            .get(&want)
+            .or_else(|| self.icao.get(&want).and_then(|faa| self.airports.get(faa)))
+            .or_else(|| self.airports.get(&norm_airport_for_routes(&want)))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Read every airport's records out of a d-TPP metafile. `pdf_base` is joined with each
+/// record's `pdf_name` to build its URL.
+fn parse_dtpp_metafile(xml: &str, pdf_base: &str) -> Result<DtppMetafile, Box<dyn std::error::Error>> {
+    let doc = roxmltree::Document::parse(xml)?;
+    let cycle = doc.root_element().attribute("cycle").map(|c| c.trim().to_string());
+    let mut airports: HashMap<String, Vec<ChartInfo>> = HashMap::new();
+    let mut icao_map = HashMap::new();
+    for apt in doc.descendants().filter(|n| n.has_tag_name("airport_name")) {
+        let faa = apt.attribute("apt_ident").unwrap_or("").trim().to_uppercase();
+        let icao = apt.attribute("icao_ident").unwrap_or("").trim().to_uppercase();
+        if faa.is_empty() {
+            continue;
+        }
+        if !icao.is_empty() {
+            icao_map.insert(icao.clone(), faa.clone());
+        }
+        let out = airports.entry(faa.clone()).or_default();
+        for rec in apt.children().filter(|n| n.has_tag_name("record")) {
+            let field = |name: &str| {
+                rec.children()
+                    .find(|n| n.has_tag_name(name))
+                    .and_then(|n| n.text())
+                    .unwrap_or("")
+                    .trim()
+                    .to_string()
+            };
+            let pdf_name = field("pdf_name");
+            let non_empty = |v: String| Some(v).filter(|v| !v.is_empty());
+            out.push(ChartInfo {
+                chart_name: field("chart_name"),
+                chart_code: field("chart_code"),
+                pdf_path: format!("{}/{}", pdf_base.trim_end_matches('/'), pdf_name),
+                _faa_ident: faa.clone(),
+                _icao_ident: icao.clone(),
+                amendment: non_empty(field("amdtnum")),
+                amendment_date: non_empty(field("amdtdate")),
+                user_action: UserAction::from_flag(&field("useraction")),
+            });
+        }
+    }
+    Ok(DtppMetafile {
+        cycle,
+        airports,
+        icao: icao_map,
+    })
+}
+
+/// A local directory of chart PDFs named after their chart titles.
+struct LocalDirProvider {
+    dir: PathBuf,
+}
+
+impl ChartProvider for LocalDirProvider {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn describe(&self) -> String {
+        format!("local ({})", self.dir.display())
+    }
+
+    fn fetch_charts<'a>(
+        &'a self,
+        _client: &'a reqwest::Client,
+        airport: &'a str,
+        cycle: Option<&'a AiracCycle>,
+        verbose: bool,
+    ) -> LocalBoxFuture<'a, Result<Vec<ChartInfo>, Box<dyn std::error::Error>>> {
+        Box::pin(async move {
+            let cycle = cycle.copied().unwrap_or_else(AiracCycle::current);
+            let up = airport.trim().to_uppercase();
+            let mut names = vec![up.clone()];
+            if up.len() == 3 {
+                names.push(format!("K{}", up));
+            } else if up.len() == 4 && up.starts_with('K') {
+                names.push(up[1..].to_string());
+            }
+            let roots = [self.dir.join(cycle.ident()), self.dir.clone()];
+            let Some(dir) = roots
+                .iter()
+                .flat_map(|r| names.iter().map(move |n| r.join(n)))
+                .find(|d| d.is_dir())
+            else {
+                return Ok(Vec::new());
+            };
+            if verbose {
+                eprintln!("reading charts from {}", dir.display());
+            }
+            let mut out = Vec::new();
+            for e in fs::read_dir(&dir)?.flatten() {
+                let path = e.path();
+                let is_pdf = path
+                    .extension()
+                    .map(|x| x.eq_ignore_ascii_case("pdf"))
+                    .unwrap_or(false);
+                if !is_pdf {
+                    continue;
+                }
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                let chart_name = stem.replace('_', " ").trim().to_uppercase();
+                out.push(ChartInfo {
+                    chart_code: ChartType::infer(&chart_name).code().to_string(),
+                    chart_name,
+                    pdf_path: format!("file://{}", fs::canonicalize(&path)?.display()),
+                    _faa_ident: norm_airport_for_routes(&up),
+                    _icao_ident: String::new(),
+                    ..Default::default()
+                });
+            }
+            out.sort_by(|a, b| a.chart_name.cmp(&b.chart_name));
+            Ok(out)
+        })
+    }
+}
+
+fn chart_provider(cfg: &ChartsConfig) -> Box<dyn ChartProvider> {
+    match cfg.provider {
+        ChartProviderKind::Aviationapi => {
+            let default_base = "https://api-v2.aviationapi.com/v2";
+            let base = std::env::var("ZDC_CHARTS_BASE")
+                .ok()
+                .or_else(|| cfg.base_url.clone())
+                .unwrap_or_else(|| default_base.into());
+            Box::new(AviationApiProvider { base })
+        }
+        ChartProviderKind::Dtpp => Box::new(DtppProvider {
+            metafile: cfg
+                .dtpp_metafile
+                .clone()
+                .unwrap_or_else(|| DEFAULT_DTPP_METAFILE.to_string()),
+            parsed: Default::default(),
+        }),
+        ChartProviderKind::Local => Box::new(LocalDirProvider {
+            dir: cfg
+                .local_dir
+                .clone()
+                .unwrap_or_else(|| cache_root().join("library")),
+        }),
+    }
+}
+
 fn find_chart_by_name(
     charts: &[ChartInfo],
     query: &ChartQuery,
@@ -381,14 +1327,13 @@ fn find_chart_by_name(
     }
     matches.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
     let best = matches[0].clone();
-    if (best.score - 1.0).abs() < std::f64::EPSILON {
+    if (best.score - 1.0).abs() < f64::EPSILON {
         return (Some(best.chart.clone()), matches);
     }
 
     if query_tokens.len() > 1 {
         let full_matches: Vec<ChartMatch> = matches
             .iter()
-            .cloned()
             .filter(|m| {
                 let chart_tokens: HashSet<_> = token_re
                     .find_iter(&m.chart.chart_name.to_uppercase())
@@ -398,6 +1343,7 @@ fn find_chart_by_name(
                     .iter()
                     .all(|qt| chart_tokens.iter().any(|ct| ct == qt || ct.contains(qt)))
             })
+            .cloned()
             .collect();
         if full_matches.len() == 1 {
             return (Some(full_matches[0].chart.clone()), matches);
@@ -420,18 +1366,88 @@ fn find_chart_by_name(
     (Some(best.chart.clone()), matches)
 }
 
-fn find_all_chart_pages(charts: &[ChartInfo], base_chart: &ChartInfo) -> Vec<ChartInfo> {
-    let mut pages: Vec<(usize, ChartInfo)> = Vec::new();
-    let base_name = if let Some(pos) = base_chart.chart_name.find(", CONT.") {
-        base_chart.chart_name[..pos].to_string()
-    } else {
-        base_chart.chart_name.clone()
-    };
-    for c in charts.iter() {
-        if c.chart_name == base_name {
-            pages.push((0, c.clone()));
-            continue;
-        }
+/// Canonical runway designator: no leading zero, uppercase side letter (`01R` -> `1R`).
+fn normalize_runway(s: &str) -> Option<String> {
+    static RUNWAY: OnceLock<Regex> = OnceLock::new();
+    let up = s.trim().to_uppercase();
+    let caps = metar_regex(&RUNWAY, r"^0*(\d{1,2})([LCR]?)$").captures(&up)?;
+    let num: u32 = caps[1].parse().ok()?;
+    if !(1..=36).contains(&num) {
+        return None;
+    }
+    Some(format!("{}{}", num, &caps[2]))
+}
+
+/// The runway in a query that asks only for a runway, e.g. `19C`, `RWY 1`, `approaches 1`.
+fn runway_query(query: &[String]) -> Option<String> {
+    let mut runway = None;
+    for tok in query.iter().flat_map(|q| q.split_whitespace()) {
+        match tok.to_uppercase().as_str() {
+            "RWY" | "RUNWAY" | "APPROACH" | "APPROACHES" | "APP" | "APPS" | "IAP" | "IAPS" => {}
+            other => {
+                if runway.is_some() {
+                    return None;
+                }
+                runway = Some(normalize_runway(other)?);
+            }
+        }
+    }
+    runway
+}
+
+/// Runways named in a chart title, e.g. `ILS OR LOC RWY 01R` -> `["1R"]`,
+/// `VOR RWYS 1/19` -> `["1", "19"]`.
+fn chart_runways(name: &str) -> Vec<String> {
+    static RWYS: OnceLock<Regex> = OnceLock::new();
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    let up = name.to_uppercase();
+    let re = metar_regex(&RWYS, r"\bRWYS?\s+(\d{1,2}[LCR]?(?:\s*(?:[-/,&]|AND)\s*\d{1,2}[LCR]?)*)");
+    let tok = metar_regex(&TOKEN, r"\d{1,2}[LCR]?");
+    re.captures_iter(&up)
+        .flat_map(|c| {
+            tok.find_iter(c.get(1).unwrap().as_str())
+                .filter_map(|m| normalize_runway(m.as_str()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Every approach chart (first pages only) that serves `runway`, sorted by title.
+/// A runway without a side letter matches all parallels (`1` -> `1L`, `1C`, `1R`).
+fn approaches_for_runway(charts: &[ChartInfo], runway: &str) -> Vec<ChartInfo> {
+    let mut out: Vec<ChartInfo> = charts
+        .iter()
+        .filter(|c| !c.chart_name.contains(", CONT."))
+        .filter(|c| {
+            c.chart_type() == ChartType::Iap
+                || (c.chart_type() == ChartType::Unknown
+                    && ChartType::infer(&c.chart_name) == ChartType::Iap)
+        })
+        .filter(|c| {
+            chart_runways(&c.chart_name).iter().any(|r| {
+                r == runway
+                    || (runway.ends_with(|ch: char| ch.is_ascii_digit())
+                        && r.trim_end_matches(['L', 'C', 'R']) == runway)
+            })
+        })
+        .cloned()
+        .collect();
+    out.sort_by(|a, b| a.chart_name.cmp(&b.chart_name));
+    out
+}
+
+fn find_all_chart_pages(charts: &[ChartInfo], base_chart: &ChartInfo) -> Vec<ChartInfo> {
+    let mut pages: Vec<(usize, ChartInfo)> = Vec::new();
+    let base_name = if let Some(pos) = base_chart.chart_name.find(", CONT.") {
+        base_chart.chart_name[..pos].to_string()
+    } else {
+        base_chart.chart_name.clone()
+    };
+    for c in charts.iter() {
+        if c.chart_name == base_name {
+            pages.push((0, c.clone()));
+            continue;
+        }
         let prefix = format!("{}, CONT.", base_name);
         if c.chart_name.starts_with(&prefix) {
             if let Some(pos) = c.chart_name.find(", CONT.") {
@@ -470,6 +1486,464 @@ fn absolute_pdf_url(base: &str, pdf_path: &str) -> String {
     }
 }
 
+fn cache_root() -> PathBuf {
+    if let Ok(p) = std::env::var("ZDC_CACHE_DIR") {
+        return PathBuf::from(p);
+    }
+    if let Ok(xdg) = std::env::var("XDG_CACHE_HOME") {
+        return PathBuf::from(xdg).join("zdc");
+    }
+    if let Some(mut home) = dirs::home_dir() {
+        home.push(".cache");
+        home.push("zdc");
+        return home;
+    }
+    PathBuf::from(".zdc-cache")
+}
+
+/// Cached chart listing for one airport in one AIRAC cycle.
+#[derive(Deserialize, Serialize)]
+struct ChartCacheEntry {
+    #[serde(default)]
+    provider: String,
+    cycle: String,
+    fetched_at: i64,
+    charts: Vec<ChartInfo>,
+}
+
+fn chart_cache_dir(cycle: &AiracCycle, airport: &str) -> PathBuf {
+    airport_cycle_dir(&cache_root().join("charts"), cycle, airport)
+}
+
+fn airport_cycle_dir(root: &Path, cycle: &AiracCycle, airport: &str) -> PathBuf {
+    root.join(cycle.ident()).join(airport.trim().to_uppercase())
+}
+
+fn load_cached_charts(cycle: &AiracCycle, airport: &str) -> Option<ChartCacheEntry> {
+    read_chart_cache(&cache_root().join("charts"), cycle, airport)
+}
+
+fn read_chart_cache(root: &Path, cycle: &AiracCycle, airport: &str) -> Option<ChartCacheEntry> {
+    let path = airport_cycle_dir(root, cycle, airport).join("charts.json");
+    let s = fs::read_to_string(path).ok()?;
+    let entry: ChartCacheEntry = serde_json::from_str(&s).ok()?;
+    if entry.cycle != cycle.ident() || entry.charts.is_empty() {
+        return None;
+    }
+    Some(entry)
+}
+
+fn store_cached_charts(
+    provider: &str,
+    cycle: &AiracCycle,
+    airport: &str,
+    charts: &[ChartInfo],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = chart_cache_dir(cycle, airport);
+    fs::create_dir_all(&dir)?;
+    let entry = ChartCacheEntry {
+        provider: provider.to_string(),
+        cycle: cycle.ident(),
+        fetched_at: Utc::now().timestamp(),
+        charts: charts.to_vec(),
+    };
+    // write then rename so a reader never sees a half-written listing
+    let tmp = dir.join("charts.json.part");
+    fs::write(&tmp, serde_json::to_string(&entry)?)?;
+    fs::rename(&tmp, dir.join("charts.json"))?;
+    Ok(())
+}
+
+/// Drop cached cycles (chart listings, PDFs and d-TPP metafiles) that expired before `current` took effect.
+fn prune_chart_cache(current: &AiracCycle) {
+    for kind in ["charts", "dtpp"] {
+        prune_cycle_dirs(&cache_root().join(kind), current);
+    }
+}
+
+/// Remove the `<YYNN>` directories under `root` older than `current`.
+fn prune_cycle_dirs(root: &Path, current: &AiracCycle) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for e in entries.flatten() {
+        let name = e.file_name().to_string_lossy().to_string();
+        if let Ok(c) = AiracCycle::from_ident(&name)
+            && c < *current
+        {
+            fs::remove_dir_all(e.path()).ok();
+        }
+    }
+}
+
+fn pdf_file_name(url: &str) -> String {
+    let last = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(url)
+        .rsplit('/')
+        .next()
+        .unwrap_or("chart.pdf");
+    let mut name: String = last
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if name.is_empty() {
+        name = "chart".to_string();
+    }
+    if !name.to_lowercase().ends_with(".pdf") {
+        name.push_str(".pdf");
+    }
+    name
+}
+
+/// Where the PDF behind `url` lives locally: the file itself for `file://` URLs,
+/// otherwise its slot in the cycle cache.
+fn cached_pdf_path(cycle: &AiracCycle, airport: &str, url: &str) -> PathBuf {
+    if let Some(local) = url.strip_prefix("file://") {
+        return PathBuf::from(local);
+    }
+    chart_cache_dir(cycle, airport).join("pdf").join(pdf_file_name(url))
+}
+
+/// Return the local path of a chart PDF, downloading it into the cycle cache if needed.
+async fn cached_pdf(
+    client: &reqwest::Client,
+    cycle: &AiracCycle,
+    airport: &str,
+    url: &str,
+    refresh: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = cached_pdf_path(cycle, airport, url);
+    if url.starts_with("file://") || (!refresh && path.exists()) {
+        return Ok(path);
+    }
+    let resp = client
+        .get(url)
+        .header("User-Agent", "ZDC-Chart-CLI/1.0")
+        .send()
+        .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(Box::<dyn std::error::Error>::from(format!(
+            "download failed {}: {}",
+            status, url
+        )));
+    }
+    let bytes = resp.bytes().await?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // write then rename so an interrupted download never leaves a truncated PDF behind
+    let tmp = path.with_extension("pdf.part");
+    fs::write(&tmp, &bytes)?;
+    fs::rename(&tmp, &path)?;
+    Ok(path)
+}
+
+/// A cached API response with the time it was fetched.
+#[derive(Deserialize, Serialize)]
+struct CachedResponse {
+    fetched_at: i64,
+    data: Value,
+}
+
+fn response_cache_path(kind: &str, key: &str) -> PathBuf {
+    cache_root()
+        .join(kind)
+        .join(format!("{}.json", key.trim().to_uppercase()))
+}
+
+fn read_cached_response(kind: &str, key: &str) -> Option<CachedResponse> {
+    let s = fs::read_to_string(response_cache_path(kind, key)).ok()?;
+    serde_json::from_str(&s).ok()
+}
+
+fn write_cached_response(kind: &str, key: &str, data: &Value) -> Result<(), Box<dyn std::error::Error>> {
+    let path = response_cache_path(kind, key);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let entry = CachedResponse {
+        fetched_at: Utc::now().timestamp(),
+        data: data.clone(),
+    };
+    fs::write(path, serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+fn format_age(fetched_at: i64) -> String {
+    let secs = (Utc::now().timestamp() - fetched_at).max(0);
+    let (d, h, m) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    if d > 0 {
+        format!("{}d {}h", d, h)
+    } else if h > 0 {
+        format!("{}h {}m", h, m)
+    } else {
+        format!("{}m", m)
+    }
+}
+
+fn print_stale_notice(what: &str, fetched_at: i64) {
+    eprintln!(
+        "[cached] {} fetched {} ({} old)",
+        what,
+        format_unix(fetched_at),
+        format_age(fetched_at)
+    );
+}
+
+/// Concatenate the pages of several PDFs, in order, into a single document at `out`.
+fn merge_pdfs(paths: &[PathBuf], out: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let mut max_id = 1;
+    let mut pages: Vec<(ObjectId, Object)> = Vec::new();
+    let mut objects: BTreeMap<ObjectId, Object> = BTreeMap::new();
+    for path in paths {
+        let mut doc = Document::load(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        doc.renumber_objects_with(max_id);
+        max_id = doc.max_id + 1;
+        for (_, id) in doc.get_pages() {
+            pages.push((id, doc.get_object(id)?.to_owned()));
+        }
+        objects.extend(doc.objects);
+    }
+
+    let mut merged = Document::with_version("1.5");
+    let mut catalog: Option<(ObjectId, Object)> = None;
+    let mut page_tree: Option<(ObjectId, Object)> = None;
+    for (id, object) in objects {
+        match object.type_name().unwrap_or(b"") {
+            b"Catalog" => {
+                if catalog.is_none() {
+                    catalog = Some((id, object));
+                }
+            }
+            b"Pages" => {
+                if page_tree.is_none() {
+                    page_tree = Some((id, object));
+                }
+            }
+            // pages are re-parented below; outlines would point into the old page trees
+            b"Page" | b"Outlines" | b"Outline" => {}
+            _ => {
+                merged.objects.insert(id, object);
+            }
+        }
+    }
+    let (catalog_id, catalog) = catalog.ok_or("merged PDF has no catalog")?;
+    let (tree_id, tree) = page_tree.ok_or("merged PDF has no page tree")?;
+
+    for (id, page) in pages.iter() {
+        let mut dict = page.as_dict()?.clone();
+        dict.set("Parent", tree_id);
+        merged.objects.insert(*id, Object::Dictionary(dict));
+    }
+    let mut tree = tree.as_dict()?.clone();
+    tree.set("Count", pages.len() as u32);
+    tree.set(
+        "Kids",
+        pages.iter().map(|(id, _)| Object::Reference(*id)).collect::<Vec<_>>(),
+    );
+    merged.objects.insert(tree_id, Object::Dictionary(tree));
+
+    let mut catalog = catalog.as_dict()?.clone();
+    catalog.set("Pages", tree_id);
+    catalog.remove(b"Outlines");
+    merged.objects.insert(catalog_id, Object::Dictionary(catalog));
+    merged.trailer.set("Root", catalog_id);
+    merged.max_id = merged.objects.keys().map(|(n, _)| *n).max().unwrap_or(0);
+    merged.renumber_objects();
+    merged.compress();
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    merged.save(out)?;
+    Ok(())
+}
+
+/// Pixels per PDF point when rasterizing a chart page.
+const RENDER_SCALE: f32 = 2.0;
+
+impl RenderMode {
+    /// Turn `Auto` into a concrete mode: ZDC_GRAPHICS first, then whatever the terminal
+    /// advertises, then plain text.
+    fn resolve(self) -> RenderMode {
+        if self != RenderMode::Auto {
+            return self;
+        }
+        if let Ok(v) = std::env::var("ZDC_GRAPHICS")
+            && let Ok(mode) = RenderMode::from_str(v.trim(), true)
+            && mode != RenderMode::Auto
+        {
+            return mode;
+        }
+        let var = |k: &str| std::env::var(k).unwrap_or_default();
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || std::env::var_os("KITTY_WINDOW_ID").is_some()
+        {
+            return RenderMode::Kitty;
+        }
+        // LC_TERMINAL survives ssh (AcceptEnv LC_*), TERM_PROGRAM usually does not
+        if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+            return RenderMode::Iterm;
+        }
+        if term.contains("sixel") || term.starts_with("foot") || term == "mlterm" {
+            return RenderMode::Sixel;
+        }
+        RenderMode::Text
+    }
+}
+
+/// Text layer of every page in the PDF at `path`.
+fn pdf_text(path: &PathBuf) -> Result<String, Box<dyn std::error::Error>> {
+    let doc = Document::load(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let pages: Vec<u32> = doc.get_pages().keys().copied().collect();
+    Ok(doc.extract_text(&pages)?)
+}
+
+/// Wrap an escape sequence so tmux hands it to the outer terminal untouched.
+fn tmux_passthrough(seq: &str) -> String {
+    if std::env::var_os("TMUX").is_none() {
+        return seq.to_string();
+    }
+    format!("\x1bPtmux;{}\x1b\\", seq.replace('\x1b', "\x1b\x1b"))
+}
+
+/// kitty graphics protocol: base64 PNG sent in 4096-byte chunks.
+fn kitty_image(png: &[u8]) -> String {
+    use base64::Engine;
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(4096)
+        .map(|c| std::str::from_utf8(c).unwrap_or_default())
+        .collect();
+    let mut out = String::with_capacity(data.len() + chunks.len() * 32);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let seq = if i == 0 {
+            format!("\x1b_Ga=T,f=100,m={more};{chunk}\x1b\\")
+        } else {
+            format!("\x1b_Gm={more};{chunk}\x1b\\")
+        };
+        out.push_str(&tmux_passthrough(&seq));
+    }
+    out
+}
+
+/// iTerm2 inline image (OSC 1337), also understood by WezTerm.
+fn iterm_image(png: &[u8]) -> String {
+    use base64::Engine;
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    tmux_passthrough(&format!(
+        "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        data
+    ))
+}
+
+/// Encode opaque RGBA pixels as DEC sixel using a fixed 6x6x6 colour cube.
+fn sixel_image(width: usize, height: usize, rgba: &[u8]) -> String {
+    let level = |v: u8| (u16::from(v) * 5 + 127) / 255;
+    let idx: Vec<u8> = rgba
+        .chunks_exact(4)
+        .map(|p| (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as u8)
+        .collect();
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    for i in 0..216u16 {
+        out.push_str(&format!("#{};2;{};{};{}", i, i / 36 * 20, i / 6 % 6 * 20, i % 6 * 20));
+    }
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let mut used = [false; 216];
+        for &c in &idx[band * width..(band + rows) * width] {
+            used[c as usize] = true;
+        }
+        let mut first = true;
+        for colour in (0..216u8).filter(|c| used[*c as usize]) {
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            out.push_str(&format!("#{colour}"));
+            let mut cols: Vec<char> = (0..width)
+                .map(|x| {
+                    let bits = (0..rows)
+                        .filter(|dy| idx[(band + dy) * width + x] == colour)
+                        .fold(0u8, |acc, dy| acc | (1 << dy));
+                    char::from(63 + bits)
+                })
+                .collect();
+            // trailing blanks are implied by the next `$`/`-`
+            while cols.last() == Some(&'?') {
+                cols.pop();
+            }
+            let mut x = 0;
+            while x < cols.len() {
+                let run = cols[x..].iter().take_while(|c| **c == cols[x]).count();
+                if run > 3 {
+                    out.push_str(&format!("!{}{}", run, cols[x]));
+                } else {
+                    out.extend(std::iter::repeat_n(cols[x], run));
+                }
+                x += run;
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Draw every page of the PDF at `path` on stdout using `mode`.
+fn render_pdf(path: &PathBuf, mode: RenderMode) -> Result<(), Box<dyn std::error::Error>> {
+    use hayro::hayro_interpret::InterpreterSettings;
+    use hayro::vello_cpu::color::palette::css::WHITE;
+
+    if mode == RenderMode::Text {
+        println!("{}", pdf_text(path)?.trim_end());
+        return Ok(());
+    }
+    let data = fs::read(path)?;
+    let pdf = hayro::hayro_syntax::Pdf::new(data)
+        .map_err(|e| format!("failed to parse {}: {:?}", path.display(), e))?;
+    let cache = hayro::RenderCache::new();
+    let pixmap_settings = hayro::PixmapSettings {
+        x_scale: RENDER_SCALE,
+        y_scale: RENDER_SCALE,
+        bg_color: WHITE,
+    };
+    let mut stdout = std::io::stdout().lock();
+    for page in pdf.pages().iter() {
+        let pixmap = hayro::render(
+            page,
+            &cache,
+            &InterpreterSettings::default(),
+            &hayro::RenderSettings::default(),
+            &pixmap_settings,
+        );
+        let seq = match mode {
+            RenderMode::Sixel => sixel_image(
+                usize::from(pixmap.width()),
+                usize::from(pixmap.height()),
+                pixmap.data_as_u8_slice(),
+            ),
+            RenderMode::Iterm => iterm_image(&pixmap.into_png()?),
+            _ => kitty_image(&pixmap.into_png()?),
+        };
+        stdout.write_all(seq.as_bytes())?;
+        writeln!(stdout)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
 fn load_or_create_config(path: &PathBuf) -> Config {
     if path.exists() {
         let s = fs::read_to_string(path).expect("failed to read config file");
@@ -477,6 +1951,10 @@ fn load_or_create_config(path: &PathBuf) -> Config {
     } else {
         let mut default = Config {
             pubs: HashMap::new(),
+            settings: Settings::default(),
+            groups: HashMap::new(),
+            charts: ChartsConfig::default(),
+            atis: HashMap::new(),
         };
         default.pubs.insert("the_fox".to_string(), "https://example.com/the_fox"
             .to_string());
@@ -492,7 +1970,7 @@ fn load_or_create_config(path: &PathBuf) -> Config {
 }
 
 fn normalize_alias(s: &str) -> String {
-    s.to_lowercase().replace('-', "_").replace(' ', "_")
+    s.to_lowercase().replace(['-', ' '], "_")
 }
 
 fn norm_airport_for_routes(src: &str) -> String {
@@ -515,11 +1993,15 @@ async fn fetch_awc(
     endpoint: &str,
     ids: &str,
     format: &str,
+    hours: Option<u32>,
 ) -> Result<Value, Box<dyn std::error::Error>> {
-    let url = format!(
+    let mut url = format!(
         "https://aviationweather.gov/api/data/{}?ids={}&format={}",
         endpoint, ids, format
     );
+    if let Some(h) = hours {
+        url.push_str(&format!("&hours={}", h));
+    }
     let resp = client.get(&url).send().await?;
     let status = resp.status();
     let body = resp.text().await?;
@@ -536,6 +2018,86 @@ async fn fetch_awc(
     Ok(json)
 }
 
+/// `fetch_awc` backed by the response cache: offline reads only the cache, online
+/// refreshes it and falls back to it when the request fails. Reports are cached per
+/// station, so any subset of earlier lookups can be answered offline.
+async fn fetch_awc_cached(
+    client: &reqwest::Client,
+    endpoint: &str,
+    ids: &str,
+    hours: Option<u32>,
+    offline: bool,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let kind = format!("wx/{}", endpoint);
+    let mut what = format!("{} {}", endpoint.to_uppercase(), ids.to_uppercase());
+    if let Some(h) = hours {
+        what.push_str(&format!(" ({h}h)"));
+    }
+    let stations: Vec<String> = ids
+        .split(',')
+        .map(|s| s.trim().to_uppercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    // history is cached apart from the latest report
+    let key = |id: &str| match hours {
+        Some(h) => format!("{id}-{h}h"),
+        None => id.to_string(),
+    };
+    let from_cache = || {
+        let mut data = Vec::new();
+        let mut oldest: Option<i64> = None;
+        for id in stations.iter() {
+            if let Some(c) = read_cached_response(&kind, &key(id)) {
+                oldest = Some(oldest.map_or(c.fetched_at, |o| o.min(c.fetched_at)));
+                data.extend(into_vec(c.data));
+            }
+        }
+        oldest.map(|t| (Value::Array(data), t))
+    };
+    if offline {
+        return Ok(match from_cache() {
+            Some((data, fetched_at)) => {
+                print_stale_notice(&what, fetched_at);
+                data
+            }
+            None => Value::Array(Vec::new()),
+        });
+    }
+    match fetch_awc(client, endpoint, ids, "json", hours).await {
+        Ok(v) => {
+            let entries = into_vec(v.clone());
+            if let [id] = stations.as_slice() {
+                if !entries.is_empty() {
+                    write_cached_response(&kind, &key(id), &v).ok();
+                }
+            } else {
+                for id in stations.iter() {
+                    let mine: Vec<Value> = entries
+                        .iter()
+                        .filter(|e| get_str_field(e, "icaoId").is_some_and(|i| i.eq_ignore_ascii_case(id)))
+                        .cloned()
+                        .collect();
+                    if !mine.is_empty() {
+                        write_cached_response(&kind, &key(id), &Value::Array(mine)).ok();
+                    }
+                }
+            }
+            Ok(v)
+        }
+        Err(e) => match from_cache() {
+            Some((data, fetched_at)) => {
+                eprintln!("request failed: {e}");
+                print_stale_notice(&what, fetched_at);
+                Ok(data)
+            }
+            None => Err(Box::<dyn std::error::Error>::from(format!(
+                "could not fetch {} and nothing is cached: {}",
+                what, e
+            ))),
+        },
+    }
+}
+
 fn into_vec(v: Value) -> Vec<Value> {
     match v {
         Value::Array(a) => a,
@@ -559,10 +2121,8 @@ fn get_str_field(v: &Value, k: &str) -> Option<String> {
             Some(s.to_string())
         } else if let Some(i) = x.as_i64() {
             Some(i.to_string())
-        } else if let Some(f) = x.as_f64() {
-            Some(format!("{}", f))
         } else {
-            None
+            x.as_f64().map(|f| format!("{}", f))
         }
     })
 }
@@ -582,10 +2142,8 @@ fn build_metar_table(m: &Value) -> Table {
     let wdir = m.get("wdir").and_then(|x| {
         if let Some(s) = x.as_str() {
             Some(s.to_string())
-        } else if let Some(i) = x.as_i64() {
-            Some(i.to_string())
         } else {
-            None
+            x.as_i64().map(|i| i.to_string())
         }
     });
     let wspd = m.get("wspd").and_then(|x| x.as_f64());
@@ -674,374 +2232,4173 @@ fn build_metar_table(m: &Value) -> Table {
     table
 }
 
-fn build_taf_table(t: &Value) -> Table {
-    let mut table = Table::new();
-    table.set_header(vec!["Period", "Wind", "Vis", "Wx", "Alt", "Clouds"]);
+/// Day-of-month and UTC time from a `DDHHMMZ` group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+struct ReportTime {
+    day: u8,
+    hour: u8,
+    minute: u8,
+}
 
-    if let Some(fcsts) = t.get("fcsts").and_then(|v| v.as_array()) {
-        for f in fcsts {
-            let from = f
-                .get("timeFrom")
-                .and_then(|n| n.as_i64())
-                .map(format_unix)
-                .unwrap_or_default();
-            let to = f
-                .get("timeTo")
-                .and_then(|n| n.as_i64())
-                .map(format_unix)
-                .unwrap_or_default();
+impl std::fmt::Display for ReportTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}{:02}{:02}Z", self.day, self.hour, self.minute)
+    }
+}
 
-            let wdir = f
-                .get("wdir")
-                .and_then(|x| x.as_str().map(|s| s.to_string()))
-                .or_else(|| f.get("wdir").and_then(|x| x.as_i64().map(|i| i.to_string())));
-            let wspd = f.get("wspd").and_then(|n| n.as_f64());
-            let wgst = f.get("wgst").and_then(|n| n.as_f64());
-            let mut wind_parts: Vec<String> = Vec::new();
-            if let Some(w) = wdir {
-                wind_parts.push(w);
-            }
-            if let Some(s) = wspd {
-                wind_parts.push(format!("{} kt", s.round() as i64));
-            }
-            if let Some(g) = wgst {
-                wind_parts.push(format!("G{} kt", g.round() as i64));
-            }
-            let wind = wind_parts.join(" ");
+impl ReportTime {
+    /// The UTC instant this day/time most plausibly means, seen from `now`
+    /// (reports only carry the day of month, so pick the nearest month).
+    fn resolve(&self, now: chrono::DateTime<Utc>) -> Option<chrono::DateTime<Utc>> {
+        let month_start = NaiveDate::from_ymd_opt(now.year(), now.month(), 1)?;
+        [-1, 0, 1]
+            .into_iter()
+            .filter_map(|offset| {
+                let first = if offset < 0 {
+                    month_start.checked_sub_months(chrono::Months::new(1))?
+                } else {
+                    month_start.checked_add_months(chrono::Months::new(offset as u32))?
+                };
+                let date = first.with_day(u32::from(self.day))?;
+                // hour 24 is midnight at the end of the day
+                let at = date.and_hms_opt(0, 0, 0)?
+                    + Duration::hours(i64::from(self.hour))
+                    + Duration::minutes(i64::from(self.minute));
+                Some(Utc.from_utc_datetime(&at))
+            })
+            .min_by_key(|t| (*t - now).num_minutes().abs())
+    }
+}
 
-            let vis = f
-                .get("visib")
-                .and_then(|x| {
-                    if let Some(s) = x.as_str() {
-                        Some(s.to_string())
-                    } else if let Some(n) = x.as_f64() {
-                        Some(format!("{}", n))
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or_default();
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct Wind {
+    /// `None` for VRB.
+    direction: Option<u16>,
+    speed: u16,
+    gust: Option<u16>,
+    /// `KT`, `MPS` or `KMH`, as reported.
+    unit: String,
+    /// `dddVddd` variability range.
+    variable_from: Option<u16>,
+    variable_to: Option<u16>,
+}
 
-            let wx = f
-                .get("wxString")
-                .and_then(|x| x.as_str())
-                .map(|s| s.to_string())
+impl Wind {
+    fn is_calm(&self) -> bool {
+        self.speed == 0 && self.gust.is_none()
+    }
+
+    /// A reported speed in knots, whatever unit the report used.
+    fn knots(&self, speed: u16) -> f64 {
+        match self.unit.as_str() {
+            "MPS" => speed as f64 * 1.943844,
+            "KMH" => speed as f64 / 1.852,
+            _ => speed as f64,
+        }
+    }
+}
+
+impl std::fmt::Display for Wind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_calm() {
+            return f.write_str("calm");
+        }
+        match self.direction {
+            Some(d) => write!(f, "{:03}° {} {}", d, self.speed, self.unit.to_lowercase())?,
+            None => write!(f, "variable {} {}", self.speed, self.unit.to_lowercase())?,
+        }
+        if let Some(g) = self.gust {
+            write!(f, " gusting {}", g)?;
+        }
+        if let (Some(a), Some(b)) = (self.variable_from, self.variable_to) {
+            write!(f, " (varying {:03}°-{:03}°)", a, b)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+struct Visibility {
+    statute_miles: f64,
+    /// `M1/4SM`: less than the reported value.
+    less_than: bool,
+    /// `P6SM`: more than the reported value.
+    greater_than: bool,
+}
+
+impl std::fmt::Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prefix = if self.less_than {
+            "<"
+        } else if self.greater_than {
+            ">"
+        } else {
+            ""
+        };
+        let sm = self.statute_miles;
+        if sm.fract() == 0.0 {
+            write!(f, "{prefix}{sm} SM")
+        } else {
+            write!(f, "{prefix}{:.2} SM", sm)
+        }
+    }
+}
+
+/// Runway visual range, e.g. `R01L/0600V1000FT`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct RunwayVisualRange {
+    runway: String,
+    feet: u32,
+    variable_to: Option<u32>,
+    /// `M` (below) or `P` (above) the reportable range.
+    qualifier: Option<char>,
+    /// `U`p, `D`own or `N`o change.
+    trend: Option<char>,
+}
+
+impl std::fmt::Display for RunwayVisualRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let q = match self.qualifier {
+            Some('M') => "<",
+            Some('P') => ">",
+            _ => "",
+        };
+        write!(f, "RWY {} {}{}", self.runway, q, self.feet)?;
+        if let Some(to) = self.variable_to {
+            write!(f, "-{}", to)?;
+        }
+        write!(f, " ft")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct SkyLayer {
+    /// FEW, SCT, BKN, OVC, VV, or CLR/SKC/NSC/NCD with no base.
+    cover: String,
+    base_ft: Option<u32>,
+    /// CB or TCU.
+    cloud: Option<String>,
+}
+
+impl SkyLayer {
+    fn is_ceiling(&self) -> bool {
+        matches!(self.cover.as_str(), "BKN" | "OVC" | "VV")
+    }
+}
+
+impl std::fmt::Display for SkyLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cover)?;
+        if let Some(b) = self.base_ft {
+            write!(f, " {}", b)?;
+        }
+        if let Some(c) = &self.cloud {
+            write!(f, " {}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct PeakWind {
+    direction: u16,
+    speed: u16,
+    hour: Option<u8>,
+    minute: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct WindShift {
+    hour: Option<u8>,
+    minute: u8,
+    frontal_passage: bool,
+}
+
+/// Decoded parts of the `RMK` section; `raw` keeps the whole thing.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct MetarRemarks {
+    raw: String,
+    /// AO1 or AO2.
+    station_type: Option<String>,
+    sea_level_pressure_hpa: Option<f64>,
+    /// T-group temperature/dewpoint to a tenth of a degree.
+    temperature_c: Option<f64>,
+    dewpoint_c: Option<f64>,
+    peak_wind: Option<PeakWind>,
+    wind_shift: Option<WindShift>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+enum FlightCategory {
+    Vfr,
+    Mvfr,
+    Ifr,
+    Lifr,
+}
+
+impl std::fmt::Display for FlightCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FlightCategory::Vfr => "VFR",
+            FlightCategory::Mvfr => "MVFR",
+            FlightCategory::Ifr => "IFR",
+            FlightCategory::Lifr => "LIFR",
+        })
+    }
+}
+
+impl FlightCategory {
+    /// AviationWeather's `fltCat` value.
+    fn from_awc(s: &str) -> Option<FlightCategory> {
+        match s.trim().to_uppercase().as_str() {
+            "VFR" => Some(FlightCategory::Vfr),
+            "MVFR" => Some(FlightCategory::Mvfr),
+            "IFR" => Some(FlightCategory::Ifr),
+            "LIFR" => Some(FlightCategory::Lifr),
+            _ => None,
+        }
+    }
+
+    /// Sectional-chart colours: green, blue, red, magenta.
+    fn color(self) -> Color {
+        match self {
+            FlightCategory::Vfr => Color::Green,
+            FlightCategory::Mvfr => Color::Blue,
+            FlightCategory::Ifr => Color::Red,
+            FlightCategory::Lifr => Color::Magenta,
+        }
+    }
+}
+
+/// Wind, visibility, weather and sky: the groups METARs and TAF periods share.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct Conditions {
+    wind: Option<Wind>,
+    visibility: Option<Visibility>,
+    cavok: bool,
+    /// Present weather groups as reported, e.g. `-TSRA`, `BR`.
+    weather: Vec<String>,
+    sky: Vec<SkyLayer>,
+}
+
+impl Conditions {
+    /// Lowest broken/overcast layer or vertical visibility, in feet.
+    fn ceiling_ft(&self) -> Option<u32> {
+        self.sky
+            .iter()
+            .filter(|l| l.is_ceiling())
+            .filter_map(|l| l.base_ft)
+            .min()
+    }
+
+    /// FAA categories: LIFR < 500 ft / 1 SM, IFR < 1000 / 3, MVFR <= 3000 / 5.
+    fn flight_category(&self) -> Option<FlightCategory> {
+        let ceiling = self.ceiling_ft();
+        let vis = if self.cavok {
+            Some(10.0)
+        } else {
+            self.visibility.map(|v| v.statute_miles)
+        };
+        if ceiling.is_none() && vis.is_none() {
+            return None;
+        }
+        let ceiling = ceiling.unwrap_or(u32::MAX);
+        let vis = vis.unwrap_or(f64::MAX);
+        Some(if ceiling < 500 || vis < 1.0 {
+            FlightCategory::Lifr
+        } else if ceiling < 1000 || vis < 3.0 {
+            FlightCategory::Ifr
+        } else if ceiling <= 3000 || vis <= 5.0 {
+            FlightCategory::Mvfr
+        } else {
+            FlightCategory::Vfr
+        })
+    }
+
+    fn visibility_text(&self) -> String {
+        if self.cavok {
+            "CAVOK".to_string()
+        } else {
+            self.visibility.map(|v| v.to_string()).unwrap_or_default()
+        }
+    }
+
+    fn weather_text(&self) -> String {
+        self.weather.iter().map(|w| describe_weather(w)).collect::<Vec<_>>().join(", ")
+    }
+
+    fn sky_text(&self) -> String {
+        self.sky.iter().map(|l| l.to_string()).collect::<Vec<_>>().join(", ")
+    }
+
+    /// Consume `tokens[i]` if it is a wind, visibility, weather or sky group.
+    fn parse_token(&mut self, tokens: &[&str], i: usize) -> bool {
+        static WIND: OnceLock<Regex> = OnceLock::new();
+        static VARWIND: OnceLock<Regex> = OnceLock::new();
+        static VIS_SM: OnceLock<Regex> = OnceLock::new();
+        static VIS_M: OnceLock<Regex> = OnceLock::new();
+        static WX: OnceLock<Regex> = OnceLock::new();
+        static SKY: OnceLock<Regex> = OnceLock::new();
+        let t = tokens[i];
+        if let Some(c) =
+            metar_regex(&WIND, r"^(\d{3}|VRB)(\d{2,3})(?:G(\d{2,3}))?(KT|MPS|KMH)$").captures(t)
+        {
+            self.wind = Some(Wind {
+                direction: c[1].parse().ok(),
+                speed: c[2].parse().unwrap_or(0),
+                gust: c.get(3).and_then(|g| g.as_str().parse().ok()),
+                unit: c[4].to_string(),
+                ..Default::default()
+            });
+        } else if let Some(c) = metar_regex(&VARWIND, r"^(\d{3})V(\d{3})$").captures(t)
+            && let Some(w) = self.wind.as_mut()
+        {
+            w.variable_from = c[1].parse().ok();
+            w.variable_to = c[2].parse().ok();
+        } else if t == "CAVOK" {
+            self.cavok = true;
+        } else if let Some(c) = metar_regex(&VIS_SM, r"^([MP])?(\d+/\d+|\d+)SM$").captures(t) {
+            let mut miles = parse_fraction(&c[2]).unwrap_or(0.0);
+            // "1 1/2SM": the whole miles came as the previous group
+            if c[2].contains('/')
+                && let Some(prev) = i.checked_sub(1).map(|p| tokens[p])
+                && prev.len() == 1
+                && let Ok(whole) = prev.parse::<f64>()
+            {
+                miles += whole;
+            }
+            self.visibility = Some(Visibility {
+                statute_miles: miles,
+                less_than: c.get(1).is_some_and(|q| q.as_str() == "M"),
+                greater_than: c.get(1).is_some_and(|q| q.as_str() == "P"),
+            });
+        } else if self.visibility.is_none()
+            // a bare 4-digit number is only visibility after the wind, or right after the
+            // period of a windless TAF change group ("TEMPO 1012/1016 3000 BR")
+            && (self.wind.is_some()
+                || i.checked_sub(1).and_then(|p| parse_taf_period(tokens[p])).is_some())
+            && let Some(c) = metar_regex(&VIS_M, r"^(\d{4})(NDV)?$").captures(t)
+        {
+            let meters: f64 = c[1].parse().unwrap_or(0.0);
+            self.visibility = Some(Visibility {
+                statute_miles: (meters / 1609.344 * 100.0).round() / 100.0,
+                less_than: false,
+                greater_than: meters >= 9999.0,
+            });
+        } else if let Some(c) =
+            metar_regex(&SKY, r"^(FEW|SCT|BKN|OVC|VV|CLR|SKC|NSC|NCD)(\d{3}|///)?(CB|TCU)?$").captures(t)
+        {
+            self.sky.push(SkyLayer {
+                cover: c[1].to_string(),
+                base_ft: c.get(2).and_then(|b| b.as_str().parse::<u32>().ok()).map(|b| b * 100),
+                cloud: c.get(3).map(|x| x.as_str().to_string()),
+            });
+        } else if t == "NSW"
+            || metar_regex(
+                &WX,
+                r"^(-|\+|VC)?(MI|BC|PR|DR|BL|SH|TS|FZ)?((DZ|RA|SN|SG|IC|PL|GR|GS|UP|BR|FG|FU|VA|DU|SA|HZ|PY|PO|SQ|FC|SS|DS)*)$",
+            )
+            .is_match(t)
+                && t.trim_start_matches(['-', '+']).len() >= 2
+        {
+            self.weather.push(t.to_string());
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// A METAR/SPECI decoded from its raw text.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct Metar {
+    raw: String,
+    /// METAR or SPECI.
+    kind: String,
+    station: String,
+    time: Option<ReportTime>,
+    auto: bool,
+    corrected: bool,
+    #[serde(flatten)]
+    conditions: Conditions,
+    rvr: Vec<RunwayVisualRange>,
+    temperature_c: Option<f64>,
+    dewpoint_c: Option<f64>,
+    altimeter_inhg: Option<f64>,
+    remarks: MetarRemarks,
+    /// Body groups that weren't recognised.
+    unparsed: Vec<String>,
+}
+
+const HPA_PER_INHG: f64 = 33.863886666667;
+
+impl Metar {
+    /// Temperature, preferring the remarks T-group's tenths.
+    fn temperature(&self) -> Option<f64> {
+        self.remarks.temperature_c.or(self.temperature_c)
+    }
+
+    fn dewpoint(&self) -> Option<f64> {
+        self.remarks.dewpoint_c.or(self.dewpoint_c)
+    }
+}
+
+fn metar_regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+/// `M05` -> -5.0
+fn metar_temp(s: &str) -> Option<f64> {
+    match s.strip_prefix('M') {
+        Some(n) => n.parse::<f64>().ok().map(|v| -v),
+        None => s.parse().ok(),
+    }
+}
+
+/// `1/2`, `3`, `1 1/2` (already joined) -> statute miles.
+fn parse_fraction(s: &str) -> Option<f64> {
+    let mut total = 0.0;
+    for part in s.split_whitespace() {
+        total += match part.split_once('/') {
+            Some((n, d)) => n.parse::<f64>().ok()? / d.parse::<f64>().ok()?.max(1.0),
+            None => part.parse::<f64>().ok()?,
+        };
+    }
+    Some(total)
+}
+
+/// `DDHHMMZ` -> report time.
+fn parse_report_time(t: &str) -> Option<ReportTime> {
+    static TIME: OnceLock<Regex> = OnceLock::new();
+    let c = metar_regex(&TIME, r"^(\d{2})(\d{2})(\d{2})Z$").captures(t)?;
+    Some(ReportTime {
+        day: c[1].parse().ok()?,
+        hour: c[2].parse().ok()?,
+        minute: c[3].parse().ok()?,
+    })
+}
+
+/// Decode the remarks that follow `RMK`.
+fn parse_metar_remarks(rmk: &str) -> MetarRemarks {
+    static SLP: OnceLock<Regex> = OnceLock::new();
+    static TGROUP: OnceLock<Regex> = OnceLock::new();
+    static PKWND: OnceLock<Regex> = OnceLock::new();
+    static WSHFT: OnceLock<Regex> = OnceLock::new();
+    let mut out = MetarRemarks {
+        raw: rmk.trim().to_string(),
+        ..Default::default()
+    };
+    for token in rmk.split_whitespace() {
+        if token == "AO1" || token == "AO2" {
+            out.station_type = Some(token.to_string());
+        } else if let Some(c) = metar_regex(&SLP, r"^SLP(\d{3})$").captures(token) {
+            let n: f64 = c[1].parse().unwrap_or(0.0);
+            let base = if n < 500.0 { 1000.0 } else { 900.0 };
+            out.sea_level_pressure_hpa = Some(base + n / 10.0);
+        } else if let Some(c) = metar_regex(&TGROUP, r"^T([01])(\d{3})([01])(\d{3})$").captures(token) {
+            let signed = |sign: &str, v: &str| {
+                let v = v.parse::<f64>().unwrap_or(0.0) / 10.0;
+                if sign == "1" { -v } else { v }
+            };
+            out.temperature_c = Some(signed(&c[1], &c[2]));
+            out.dewpoint_c = Some(signed(&c[3], &c[4]));
+        }
+    }
+    if let Some(c) = metar_regex(&PKWND, r"\bPK WND (\d{3})(\d{2,3})/(\d{2})?(\d{2})\b").captures(rmk) {
+        out.peak_wind = Some(PeakWind {
+            direction: c[1].parse().unwrap_or(0),
+            speed: c[2].parse().unwrap_or(0),
+            hour: c.get(3).and_then(|m| m.as_str().parse().ok()),
+            minute: c[4].parse().unwrap_or(0),
+        });
+    }
+    if let Some(c) = metar_regex(&WSHFT, r"\bWSHFT (\d{2})?(\d{2})( FROPA)?\b").captures(rmk) {
+        out.wind_shift = Some(WindShift {
+            hour: c.get(1).and_then(|m| m.as_str().parse().ok()),
+            minute: c[2].parse().unwrap_or(0),
+            frontal_passage: c.get(3).is_some(),
+        });
+    }
+    out
+}
+
+/// Decode a raw METAR/SPECI. Unknown groups end up in `unparsed` rather than failing.
+fn parse_metar(text: &str) -> Result<Metar, String> {
+    static RVR: OnceLock<Regex> = OnceLock::new();
+    static TEMP: OnceLock<Regex> = OnceLock::new();
+    static ALT: OnceLock<Regex> = OnceLock::new();
+
+    let raw = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let raw = raw.trim_end_matches('=').to_string();
+    let (body, rmk) = match raw.split_once(" RMK") {
+        Some((b, r)) => (b.to_string(), Some(r.to_string())),
+        None => (raw.clone(), None),
+    };
+    let mut tokens: Vec<&str> = body.split_whitespace().collect();
+    let mut m = Metar {
+        raw: raw.clone(),
+        kind: "METAR".to_string(),
+        ..Default::default()
+    };
+    if let Some(first) = tokens.first()
+        && (*first == "METAR" || *first == "SPECI")
+    {
+        m.kind = first.to_string();
+        tokens.remove(0);
+    }
+    let station = tokens.first().ok_or("empty report")?;
+    if station.len() != 4 || !station.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("'{}' is not a station identifier", station));
+    }
+    m.station = station.to_string();
+
+    for i in 1..tokens.len() {
+        let t = tokens[i];
+        if let Some(time) = parse_report_time(t) {
+            m.time = Some(time);
+        } else if t == "AUTO" {
+            m.auto = true;
+        } else if t == "COR" {
+            m.corrected = true;
+        } else if m.conditions.parse_token(&tokens, i) {
+            // "1 1/2SM": the "1" was set aside before its fraction showed up
+            if tokens[i].contains("/") && tokens[i].ends_with("SM") && i > 1 {
+                let prev = tokens[i - 1];
+                m.unparsed.retain(|u| u != prev);
+            }
+        } else if let Some(c) =
+            metar_regex(&RVR, r"^R(\d{2}[LCR]?)/([MP])?(\d{4})(?:V([MP])?(\d{4}))?(?:FT)?/?([UDN])?$")
+                .captures(t)
+        {
+            m.rvr.push(RunwayVisualRange {
+                runway: c[1].to_string(),
+                feet: c[3].parse().unwrap_or(0),
+                variable_to: c.get(5).and_then(|v| v.as_str().parse().ok()),
+                qualifier: c.get(2).and_then(|q| q.as_str().chars().next()),
+                trend: c.get(6).and_then(|q| q.as_str().chars().next()),
+            });
+        } else if let Some(c) = metar_regex(&TEMP, r"^(M?\d{2})/(M?\d{2})?$").captures(t) {
+            m.temperature_c = metar_temp(&c[1]);
+            m.dewpoint_c = c.get(2).and_then(|d| metar_temp(d.as_str()));
+        } else if let Some(c) = metar_regex(&ALT, r"^([AQ])(\d{4})$").captures(t) {
+            let v: f64 = c[2].parse().unwrap_or(0.0);
+            m.altimeter_inhg = Some(if &c[1] == "A" { v / 100.0 } else { v / HPA_PER_INHG });
+        } else {
+            m.unparsed.push(t.to_string());
+        }
+    }
+    if let Some(r) = rmk {
+        m.remarks = parse_metar_remarks(&r);
+    }
+    Ok(m)
+}
+
+/// TAF change indicator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+enum TafChange {
+    /// The opening forecast after the validity period.
+    Base,
+    /// `FMddhhmm`: conditions replace everything before.
+    From,
+    /// `BECMG`: a gradual change over the period.
+    Becmg,
+    /// `TEMPO`: temporary fluctuations during the period.
+    Tempo,
+    /// `PROBnn` on its own: chance of these conditions during the period.
+    Prob,
+}
+
+/// One TAF period: the base forecast or a change group.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct TafGroup {
+    change: TafChange,
+    /// PROB30/PROB40, also set for `PROB30 TEMPO`.
+    probability: Option<u8>,
+    from: Option<ReportTime>,
+    to: Option<ReportTime>,
+    #[serde(flatten)]
+    conditions: Conditions,
+    /// `WSddd/dddffKT` low-level wind shear.
+    wind_shear: Option<String>,
+    raw: String,
+    unparsed: Vec<String>,
+}
+
+impl TafGroup {
+    fn label(&self) -> String {
+        let kind = match self.change {
+            TafChange::Base => "BASE",
+            TafChange::From => "FM",
+            TafChange::Becmg => "BECMG",
+            TafChange::Tempo => "TEMPO",
+            TafChange::Prob => "",
+        };
+        match self.probability {
+            Some(p) if kind.is_empty() => format!("PROB{p}"),
+            Some(p) => format!("PROB{p} {kind}"),
+            None => kind.to_string(),
+        }
+    }
+
+    fn period(&self) -> String {
+        match (self.from, self.to) {
+            (Some(f), Some(t)) => format!("{} - {}", f, t),
+            (Some(f), None) => format!("{} -", f),
+            _ => String::new(),
+        }
+    }
+
+    /// Base and FM groups set the prevailing forecast; the rest modify it.
+    fn is_prevailing(&self) -> bool {
+        matches!(self.change, TafChange::Base | TafChange::From)
+    }
+}
+
+/// A TAF decoded from its raw text.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+struct Taf {
+    raw: String,
+    station: String,
+    issued: Option<ReportTime>,
+    valid_from: Option<ReportTime>,
+    valid_to: Option<ReportTime>,
+    amended: bool,
+    corrected: bool,
+    groups: Vec<TafGroup>,
+    remarks: String,
+}
+
+/// `DDHH/DDHH` -> (from, to).
+fn parse_taf_period(t: &str) -> Option<(ReportTime, ReportTime)> {
+    static PERIOD: OnceLock<Regex> = OnceLock::new();
+    let c = metar_regex(&PERIOD, r"^(\d{2})(\d{2})/(\d{2})(\d{2})$").captures(t)?;
+    let at = |d: &str, h: &str| {
+        Some(ReportTime {
+            day: d.parse().ok()?,
+            hour: h.parse().ok()?,
+            minute: 0,
+        })
+    };
+    Some((at(&c[1], &c[2])?, at(&c[3], &c[4])?))
+}
+
+/// Decode a raw TAF into its base forecast and change groups, in order.
+fn parse_taf(text: &str) -> Result<Taf, String> {
+    static FM: OnceLock<Regex> = OnceLock::new();
+    static PROB: OnceLock<Regex> = OnceLock::new();
+    static WS: OnceLock<Regex> = OnceLock::new();
+
+    let raw = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let raw = raw.trim_end_matches('=').to_string();
+    let (body, rmk) = match raw.split_once(" RMK ") {
+        Some((b, r)) => (b.to_string(), r.to_string()),
+        None => (raw.clone(), String::new()),
+    };
+    let mut tokens: Vec<&str> = body.split_whitespace().collect();
+    let mut taf = Taf {
+        raw: raw.clone(),
+        remarks: rmk,
+        ..Default::default()
+    };
+    if tokens.first() == Some(&"TAF") {
+        tokens.remove(0);
+    }
+    while let Some(t) = tokens.first() {
+        match *t {
+            "AMD" => taf.amended = true,
+            "COR" => taf.corrected = true,
+            _ => break,
+        }
+        tokens.remove(0);
+    }
+    let station = tokens.first().ok_or("empty TAF")?;
+    if station.len() != 4 || !station.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!("'{}' is not a station identifier", station));
+    }
+    taf.station = station.to_string();
+
+    let mut i = 1;
+    if let Some(t) = tokens.get(i).and_then(|t| parse_report_time(t)) {
+        taf.issued = Some(t);
+        i += 1;
+    }
+    if let Some((f, t)) = tokens.get(i).and_then(|t| parse_taf_period(t)) {
+        taf.valid_from = Some(f);
+        taf.valid_to = Some(t);
+        i += 1;
+    } else {
+        return Err("missing validity period (DDHH/DDHH)".to_string());
+    }
+
+    let group_text = split_taf_groups(&tokens[i..].join(" "));
+    let new_group = |change, from, to| TafGroup {
+        change,
+        probability: None,
+        from,
+        to,
+        conditions: Conditions::default(),
+        wind_shear: None,
+        raw: String::new(),
+        unparsed: Vec::new(),
+    };
+    let mut group = new_group(TafChange::Base, taf.valid_from, None);
+    while i < tokens.len() {
+        let t = tokens[i];
+        let fm = metar_regex(&FM, r"^FM(\d{2})(\d{2})(\d{2})$").captures(t);
+        let prob = metar_regex(&PROB, r"^PROB(\d{2})$").captures(t);
+        if fm.is_some() || prob.is_some() || t == "TEMPO" || t == "BECMG" {
+            taf.groups.push(std::mem::replace(&mut group, new_group(TafChange::Base, None, None)));
+            if let Some(c) = fm {
+                group.change = TafChange::From;
+                group.from = Some(ReportTime {
+                    day: c[1].parse().unwrap_or(0),
+                    hour: c[2].parse().unwrap_or(0),
+                    minute: c[3].parse().unwrap_or(0),
+                });
+            } else {
+                group.change = match t {
+                    "TEMPO" => TafChange::Tempo,
+                    "BECMG" => TafChange::Becmg,
+                    _ => TafChange::Prob,
+                };
+                if let Some(c) = prob {
+                    group.probability = c[1].parse().ok();
+                    if tokens.get(i + 1) == Some(&"TEMPO") {
+                        group.change = TafChange::Tempo;
+                        i += 1;
+                    }
+                }
+                if let Some((f, to)) = tokens.get(i + 1).and_then(|t| parse_taf_period(t)) {
+                    group.from = Some(f);
+                    group.to = Some(to);
+                    i += 1;
+                }
+            }
+        } else if metar_regex(&WS, r"^WS\d{3}/\d{5,6}KT$").is_match(t) {
+            group.wind_shear = Some(t.to_string());
+        } else if !group.conditions.parse_token(&tokens, i) {
+            group.unparsed.push(t.to_string());
+        } else if t.contains('/') && t.ends_with("SM") && i > 0 {
+            let prev = tokens[i - 1];
+            group.unparsed.retain(|u| u != prev);
+        }
+        i += 1;
+    }
+    taf.groups.push(group);
+
+    // prevailing groups run until the next FM group or the end of the TAF
+    let starts: Vec<(usize, Option<ReportTime>)> = taf
+        .groups
+        .iter()
+        .enumerate()
+        .filter(|(_, g)| g.is_prevailing())
+        .map(|(i, g)| (i, g.from))
+        .collect();
+    for (n, (idx, _)) in starts.iter().enumerate() {
+        let end = starts.get(n + 1).and_then(|(_, f)| *f).or(taf.valid_to);
+        taf.groups[*idx].to = end;
+    }
+    // keep each group's own text for compact display
+    for (g, text) in taf.groups.iter_mut().zip(group_text) {
+        g.raw = text;
+    }
+    Ok(taf)
+}
+
+/// Raw text of each TAF group, split before FM/TEMPO/BECMG/PROB markers.
+fn split_taf_groups(body: &str) -> Vec<String> {
+    let mut out: Vec<String> = vec![String::new()];
+    let tokens: Vec<&str> = body.split_whitespace().collect();
+    for (i, t) in tokens.iter().enumerate() {
+        let prev_prob = i > 0 && tokens[i - 1].starts_with("PROB");
+        let starts = (t.starts_with("FM") && t.len() == 8)
+            || t.starts_with("PROB")
+            || ((*t == "TEMPO") && !prev_prob)
+            || *t == "BECMG";
+        if starts {
+            out.push(String::new());
+        }
+        let cur = out.last_mut().unwrap();
+        if !cur.is_empty() {
+            cur.push(' ');
+        }
+        cur.push_str(t);
+    }
+    out
+}
+
+/// One row per TAF group, labelled with its change type.
+fn decoded_taf_table(taf: &Taf) -> Table {
+    let mut table = Table::new();
+    table.set_header(vec!["Type", "Period", "Wind", "Vis", "Wx", "Clouds", "Cat"]);
+    for g in taf.groups.iter() {
+        let c = &g.conditions;
+        let mut wx = c.weather_text();
+        if let Some(ws) = &g.wind_shear {
+            if !wx.is_empty() {
+                wx.push_str(", ");
+            }
+            wx.push_str(&format!("wind shear {ws}"));
+        }
+        table.add_row(vec![
+            g.label(),
+            g.period(),
+            c.wind.as_ref().map(|w| w.to_string()).unwrap_or_default(),
+            c.visibility_text(),
+            wx,
+            c.sky_text(),
+            c.flight_category().map(|c| c.to_string()).unwrap_or_default(),
+        ]);
+    }
+    table
+}
+
+/// Plain-English reading of a present weather group: `-TSRA` -> "light thunderstorm rain".
+fn describe_weather(code: &str) -> String {
+    let words: &[(&str, &str)] = &[
+        ("VC", "vicinity"), ("MI", "shallow"), ("BC", "patches of"), ("PR", "partial"),
+        ("DR", "low drifting"), ("BL", "blowing"), ("SH", "showers of"), ("TS", "thunderstorm"),
+        ("FZ", "freezing"), ("DZ", "drizzle"), ("RA", "rain"), ("SN", "snow"),
+        ("SG", "snow grains"), ("IC", "ice crystals"), ("PL", "ice pellets"), ("GR", "hail"),
+        ("GS", "small hail"), ("UP", "unknown precipitation"), ("BR", "mist"), ("FG", "fog"),
+        ("FU", "smoke"), ("VA", "volcanic ash"), ("DU", "dust"), ("SA", "sand"), ("HZ", "haze"),
+        ("PY", "spray"), ("PO", "dust whirls"), ("SQ", "squalls"), ("FC", "funnel cloud"),
+        ("SS", "sandstorm"), ("DS", "duststorm"),
+    ];
+    if code == "NSW" {
+        return "no significant weather".to_string();
+    }
+    let (intensity, rest) = match code.chars().next() {
+        Some('-') => (Some("light"), &code[1..]),
+        Some('+') => (Some("heavy"), &code[1..]),
+        _ => (None, code),
+    };
+    let mut out: Vec<&str> = intensity.into_iter().collect();
+    let mut i = 0;
+    while i + 2 <= rest.len() {
+        let part = &rest[i..i + 2];
+        out.push(words.iter().find(|(k, _)| *k == part).map(|(_, v)| *v).unwrap_or(part));
+        i += 2;
+    }
+    out.join(" ")
+}
+
+fn format_altimeter(inhg: f64) -> String {
+    format!("{:.2} inHg ({:.1} hPa)", inhg, inhg * HPA_PER_INHG)
+}
+
+fn format_temp_dew(temp: Option<f64>, dew: Option<f64>) -> String {
+    match (temp, dew) {
+        (Some(t), Some(d)) => format!(
+            "{:.1}°C/{:.1}°C ({:.0}°F/{:.0}°F)",
+            t,
+            d,
+            c_to_f(t).round(),
+            c_to_f(d).round()
+        ),
+        (Some(t), None) => format!("{:.1}°C ({:.0}°F)", t, c_to_f(t).round()),
+        _ => String::new(),
+    }
+}
+
+/// Same layout as `build_metar_table`, one row per decoded report.
+fn decoded_metar_table(reports: &[Metar]) -> Table {
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Station", "Time", "Wind", "Vis", "Wx", "Temp/Dew", "Alt", "FlightCat", "Clouds",
+    ]);
+    for m in reports {
+        let c = &m.conditions;
+        table.add_row(vec![
+            m.station.clone(),
+            m.time.map(|t| t.to_string()).unwrap_or_default(),
+            c.wind.as_ref().map(|w| w.to_string()).unwrap_or_default(),
+            c.visibility_text(),
+            c.weather_text(),
+            format_temp_dew(m.temperature(), m.dewpoint()),
+            m.altimeter_inhg.map(format_altimeter).unwrap_or_default(),
+            c.flight_category().map(|c| c.to_string()).unwrap_or_default(),
+            c.sky_text(),
+        ]);
+    }
+    table
+}
+
+/// Field-by-field breakdown of a decoded report, remarks included.
+fn metar_detail_table(m: &Metar) -> Table {
+    let mut table = Table::new();
+    table.set_header(vec!["Field", "Value"]);
+    let mut row = |k: &str, v: String| {
+        if !v.is_empty() {
+            table.add_row(vec![k.to_string(), v]);
+        }
+    };
+    let mut report = m.kind.clone();
+    if m.auto {
+        report.push_str(" (automated)");
+    }
+    if m.corrected {
+        report.push_str(" (corrected)");
+    }
+    row("Report", report);
+    row("Station", m.station.clone());
+    row("Time", m.time.map(|t| t.to_string()).unwrap_or_default());
+    let c = &m.conditions;
+    row("Wind", c.wind.as_ref().map(|w| w.to_string()).unwrap_or_default());
+    row("Visibility", c.visibility_text());
+    row("RVR", m.rvr.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(", "));
+    row("Weather", c.weather_text());
+    row("Sky", c.sky_text());
+    row("Ceiling", c.ceiling_ft().map(|c| format!("{c} ft")).unwrap_or_default());
+    row("Temp/Dew", format_temp_dew(m.temperature(), m.dewpoint()));
+    row("Altimeter", m.altimeter_inhg.map(format_altimeter).unwrap_or_default());
+    row("Flight cat", c.flight_category().map(|c| c.to_string()).unwrap_or_default());
+    let r = &m.remarks;
+    row("Station type", r.station_type.clone().unwrap_or_default());
+    row(
+        "Sea-level pressure",
+        r.sea_level_pressure_hpa.map(|p| format!("{:.1} hPa", p)).unwrap_or_default(),
+    );
+    row(
+        "Peak wind",
+        r.peak_wind
+            .as_ref()
+            .map(|p| match p.hour {
+                Some(h) => format!("{:03}° {} kt at {:02}{:02}Z", p.direction, p.speed, h, p.minute),
+                None => format!("{:03}° {} kt at :{:02}", p.direction, p.speed, p.minute),
+            })
+            .unwrap_or_default(),
+    );
+    row(
+        "Wind shift",
+        r.wind_shift
+            .as_ref()
+            .map(|w| {
+                let at = match w.hour {
+                    Some(h) => format!("{:02}{:02}Z", h, w.minute),
+                    None => format!(":{:02}", w.minute),
+                };
+                if w.frontal_passage { format!("{at} (frontal passage)") } else { at }
+            })
+            .unwrap_or_default(),
+    );
+    row("Remarks", r.raw.clone());
+    row("Not decoded", m.unparsed.join(" "));
+    table
+}
+
+fn build_taf_table(t: &Value) -> Table {
+    let mut table = Table::new();
+    table.set_header(vec!["Period", "Wind", "Vis", "Wx", "Alt", "Clouds"]);
+
+    if let Some(fcsts) = t.get("fcsts").and_then(|v| v.as_array()) {
+        for f in fcsts {
+            let from = f
+                .get("timeFrom")
+                .and_then(|n| n.as_i64())
+                .map(format_unix)
                 .unwrap_or_default();
+            let to = f
+                .get("timeTo")
+                .and_then(|n| n.as_i64())
+                .map(format_unix)
+                .unwrap_or_default();
+
+            let wdir = f
+                .get("wdir")
+                .and_then(|x| x.as_str().map(|s| s.to_string()))
+                .or_else(|| f.get("wdir").and_then(|x| x.as_i64().map(|i| i.to_string())));
+            let wspd = f.get("wspd").and_then(|n| n.as_f64());
+            let wgst = f.get("wgst").and_then(|n| n.as_f64());
+            let mut wind_parts: Vec<String> = Vec::new();
+            if let Some(w) = wdir {
+                wind_parts.push(w);
+            }
+            if let Some(s) = wspd {
+                wind_parts.push(format!("{} kt", s.round() as i64));
+            }
+            if let Some(g) = wgst {
+                wind_parts.push(format!("G{} kt", g.round() as i64));
+            }
+            let wind = wind_parts.join(" ");
+
+            let vis = f
+                .get("visib")
+                .and_then(|x| {
+                    if let Some(s) = x.as_str() {
+                        Some(s.to_string())
+                    } else {
+                        x.as_f64().map(|n| format!("{}", n))
+                    }
+                })
+                .unwrap_or_default();
+
+            let wx = f
+                .get("wxString")
+                .and_then(|x| x.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_default();
+
+            let alt = f.get("altim").and_then(|n| n.as_f64()).map(|a| {
+                if a >= 50.0 {
+                    let inhg = a * 0.029529983071445;
+                    format!("{:.1} hPa ({:.2} inHg)", a, inhg)
+                } else {
+                    let hpa = a / 0.029529983071445;
+                    format!("{:.2} inHg ({:.1} hPa)", a, hpa)
+                }
+            }).unwrap_or_default();
+
+            let clouds = f.get("clouds").and_then(|c| {
+                c.as_array().map(|arr| {
+                    arr.iter()
+                        .map(|layer| {
+                            let cover = layer.get("cover").and_then(|s| s.as_str()).unwrap_or("");
+                            let base = layer
+                                .get("base")
+                                .and_then(|n| n.as_i64().map(|b| b.to_string()))
+                                .or_else(|| {
+                                    layer
+                                        .get("base")
+                                        .and_then(|n| n.as_f64().map(|f| f.to_string()))
+                                })
+                                .unwrap_or_default();
+                            if base.is_empty() {
+                                cover.to_string()
+                            } else {
+                                format!("{}{}", cover, base)
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+            }).unwrap_or_default();
+
+            let period = if !from.is_empty() || !to.is_empty() {
+                format!("{} - {}", from, to)
+            } else {
+                "".to_string()
+            };
+
+            table.add_row(vec![
+                period.as_str(),
+                wind.as_str(),
+                vis.as_str(),
+                wx.as_str(),
+                alt.as_str(),
+                clouds.as_str(),
+            ]);
+        }
+    }
+
+    table
+}
+
+async fn handle_route(
+    client: &reqwest::Client,
+    origin: &str,
+    destination: &str,
+    raw: bool,
+    offline: bool,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let origin = norm_airport_for_routes(origin);
+    let destination = norm_airport_for_routes(destination);
+    let key = format!("{}-{}", origin, destination);
+    let what = format!("routes {} -> {}", origin, destination);
+    let json: Value = if offline {
+        match read_cached_response("routes", &key) {
+            Some(c) => {
+                print_stale_notice(&what, c.fetched_at);
+                c.data
+            }
+            None => {
+                eprintln!("No cached preferred routes for {} -> {} (offline)", origin, destination);
+                return Ok(());
+            }
+        }
+    } else {
+        let url = format!("https://api.aviationapi.com/v1/preferred-routes/search?origin={}&dest={}",
+                          origin, destination);
+        if verbose { eprintln!("GET {}", url); }
+        let fetched: Result<Value, Box<dyn std::error::Error>> = async {
+            let resp = client.get(&url).send().await?;
+            let status = resp.status();
+            let body = resp.text().await?;
+            if !status.is_success() {
+                return Err(format!("api error {}: {}", status, body).into());
+            }
+            Ok(serde_json::from_str(&body)?)
+        }
+        .await;
+        match fetched {
+            Ok(json) => {
+                write_cached_response("routes", &key, &json).ok();
+                json
+            }
+            Err(e) => match read_cached_response("routes", &key) {
+                Some(c) => {
+                    eprintln!("request failed: {e}");
+                    print_stale_notice(&what, c.fetched_at);
+                    c.data
+                }
+                None => {
+                    return Err(Box::<dyn std::error::Error>::from(format!(
+                        "could not fetch {} and nothing is cached: {}",
+                        what, e
+                    )));
+                }
+            },
+        }
+    };
+    let rows = match json {
+        Value::Array(a) => a,
+        other => vec![other],
+    };
+    if rows.is_empty() {
+        println!("No preferred routes found for {} -> {}", origin, destination);
+        return Ok(());
+    }
+    if raw {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+    let mut keys = BTreeSet::new();
+    for v in &rows {
+        if let Value::Object(map) = v {
+            for k in map.keys() {
+                keys.insert(k.clone());
+            }
+        } else {
+            keys.insert("value".to_string());
+        }
+    }
+    let headers: Vec<String> = keys.into_iter().collect();
+    let mut table = Table::new();
+    table.set_header(headers.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+    for v in rows {
+        let mut cells = Vec::with_capacity(headers.len());
+        for k in &headers {
+            let s = if let Value::Object(map) = &v {
+                let val = map.get(k).unwrap_or(&Value::Null);
+                match val {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => n.to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    Value::Array(a) => a.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "),
+                    Value::Object(_) => serde_json::to_string(val).unwrap_or_default(),
+                    Value::Null => "".to_string(),
+                }
+            } else {
+                v.to_string()
+            };
+            cells.push(s);
+        }
+        let row_refs: Vec<&str> = cells.iter().map(|s| s.as_str()).collect();
+        table.add_row(row_refs);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+async fn handle_metar(
+    client: &reqwest::Client,
+    station: &str,
+    raw: bool,
+    json: bool,
+    _verbose: bool,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut st = station.trim().to_uppercase();
+    let mut data = fetch_awc_cached(client, "metar", &st, None, offline).await?;
+    let mut arr = into_vec(data);
+    if arr.is_empty() && st.len() == 3 && !st.starts_with('K') {
+        st = format!("K{}", st);
+        data = fetch_awc_cached(client, "metar", &st, None, offline).await?;
+        arr = into_vec(data);
+    }
+    if arr.is_empty() {
+        if offline {
+            eprintln!("No cached METAR for {} (offline)", st);
+        } else {
+            eprintln!("No METAR data found for {}", st);
+        }
+        return Ok(());
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&arr)?);
+        return Ok(());
+    }
+    for entry in arr {
+        let rawtxt = get_str_field(&entry, "rawOb")
+            .or_else(|| get_str_field(&entry, "raw_text"))
+            .unwrap_or_default();
+        if !rawtxt.is_empty() {
+            println!("{}", rawtxt);
+            println!();
+        } else if raw {
+            println!("{}", serde_json::to_string_pretty(&entry)?);
+            println!();
+        }
+        let table = match parse_metar(&rawtxt) {
+            Ok(m) => decoded_metar_table(&[m]),
+            Err(_) => build_metar_table(&entry),
+        };
+        println!("{table}");
+    }
+    Ok(())
+}
+
+/// Observations from the last `hours` hours, oldest first, with a trend summary.
+async fn handle_metar_history(
+    client: &reqwest::Client,
+    station: &str,
+    hours: u32,
+    raw: bool,
+    json: bool,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let id = icao_station(station);
+    let arr = into_vec(fetch_awc_cached(client, "metar", &id, Some(hours), offline).await?);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&arr)?);
+        return Ok(());
+    }
+    let now = Utc::now();
+    let mut series: Vec<(i64, Metar)> = Vec::new();
+    for entry in arr.iter() {
+        let rawtxt = get_str_field(entry, "rawOb").unwrap_or_default();
+        let m = match parse_metar(&rawtxt) {
+            Ok(m) => m,
+            Err(e) => {
+                eprintln!("{}: cannot decode METAR ({})", id, e);
+                continue;
+            }
+        };
+        let ts = entry
+            .get("obsTime")
+            .and_then(|t| t.as_i64())
+            .or_else(|| m.time.and_then(|t| t.resolve(now)).map(|t| t.timestamp()))
+            .unwrap_or_default();
+        series.push((ts, m));
+    }
+    if series.is_empty() {
+        eprintln!("No METARs for {} in the last {} h", id, hours);
+        return Ok(());
+    }
+    series.sort_by_key(|(ts, _)| *ts);
+    if raw {
+        for (_, m) in series.iter() {
+            println!("{}", m.raw);
+        }
+        return Ok(());
+    }
+    println!("{}: {} reports in the last {} h", id, series.len(), hours);
+    let trend = metar_trend(&series);
+    let reports: Vec<Metar> = series.into_iter().map(|(_, m)| m).collect();
+    println!("{}", decoded_metar_table(&reports));
+    for line in trend {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// Ceiling, visibility, wind, pressure and category tendency between the first and last report.
+fn metar_trend(series: &[(i64, Metar)]) -> Vec<String> {
+    let (Some((t0, first)), Some((t1, last))) = (series.first(), series.last()) else {
+        return Vec::new();
+    };
+    let span_h = (t1 - t0) as f64 / 3600.0;
+    let stamp = |m: &Metar| m.time.map(|t| format!("{:02}{:02}Z", t.hour, t.minute)).unwrap_or_default();
+    let mut lines = vec![format!("Trend {} -> {} ({:.1} h)", stamp(first), stamp(last), span_h)];
+    if series.len() < 2 {
+        lines.push("  only one report".to_string());
+        return lines;
+    }
+    let (a, b) = (&first.conditions, &last.conditions);
+
+    let ceiling = match (a.ceiling_ft(), b.ceiling_ft()) {
+        (None, None) => "no ceiling".to_string(),
+        (Some(x), None) => format!("lifted (was {x} ft)"),
+        (None, Some(y)) => format!("formed at {y} ft"),
+        (Some(x), Some(y)) if y < x => format!("lowering {x} -> {y} ft"),
+        (Some(x), Some(y)) if y > x => format!("rising {x} -> {y} ft"),
+        (Some(x), _) => format!("steady at {x} ft"),
+    };
+    let lowest = series
+        .iter()
+        .filter_map(|(_, m)| m.conditions.ceiling_ft().map(|c| (c, m)))
+        .min_by_key(|(c, _)| *c);
+    match lowest {
+        Some((c, m)) if Some(c) != a.ceiling_ft() && Some(c) != b.ceiling_ft() => {
+            lines.push(format!("  Ceiling:    {ceiling}, lowest {c} ft at {}", stamp(m)))
+        }
+        _ => lines.push(format!("  Ceiling:    {ceiling}")),
+    }
+
+    let sm = |c: &Conditions| if c.cavok { Some(10.0) } else { c.visibility.map(|v| v.statute_miles) };
+    let vis = match (sm(a), sm(b)) {
+        (Some(x), Some(y)) if y > x => format!("improving {} -> {}", a.visibility_text(), b.visibility_text()),
+        (Some(x), Some(y)) if y < x => format!("deteriorating {} -> {}", a.visibility_text(), b.visibility_text()),
+        (Some(_), Some(_)) => format!("steady at {}", b.visibility_text()),
+        _ => "not reported".to_string(),
+    };
+    lines.push(format!("  Visibility: {vis}"));
+
+    let wind = match (&a.wind, &b.wind) {
+        (Some(x), Some(y)) => {
+            let mut parts = Vec::new();
+            match (x.direction.filter(|_| !x.is_calm()), y.direction.filter(|_| !y.is_calm())) {
+                (Some(d0), Some(d1)) => {
+                    let turn = (d1 as i32 - d0 as i32 + 540) % 360 - 180;
+                    parts.push(match turn {
+                        t if t >= 20 => format!("veering {:03}° -> {:03}°", d0, d1),
+                        t if t <= -20 => format!("backing {:03}° -> {:03}°", d0, d1),
+                        _ => format!("steady from {:03}°", d1),
+                    });
+                }
+                _ => parts.push(format!("{} -> {}", x, y)),
+            }
+            let (s0, s1) = (x.knots(x.speed), y.knots(y.speed));
+            if s1 - s0 >= 5.0 {
+                parts.push(format!("increasing {:.0} -> {:.0} kt", s0, s1));
+            } else if s0 - s1 >= 5.0 {
+                parts.push(format!("decreasing {:.0} -> {:.0} kt", s0, s1));
+            }
+            if let Some(g) = y.gust
+                && x.gust.is_none()
+            {
+                parts.push(format!("gusts to {} now reported", g));
+            }
+            parts.join(", ")
+        }
+        _ => "not reported".to_string(),
+    };
+    lines.push(format!("  Wind:       {wind}"));
+
+    let pressure = match (first.altimeter_inhg, last.altimeter_inhg) {
+        (Some(p0), Some(p1)) => {
+            let delta = p1 - p0;
+            let rate = if span_h > 0.0 { delta.abs() / span_h } else { 0.0 };
+            // PRESRR/PRESFR threshold: 0.06 inHg per hour
+            let how = if delta.abs() < 0.02 {
+                "steady"
+            } else if delta > 0.0 {
+                if rate >= 0.06 { "rising rapidly" } else { "rising" }
+            } else if rate >= 0.06 {
+                "falling rapidly"
+            } else {
+                "falling"
+            };
+            format!("{how}, {:.2} -> {:.2} inHg ({:+.2})", p0, p1, delta)
+        }
+        _ => "not reported".to_string(),
+    };
+    lines.push(format!("  Pressure:   {pressure}"));
+
+    let category = match (a.flight_category(), b.flight_category()) {
+        (Some(x), Some(y)) if y > x => format!("{x} -> {y} (worsening)"),
+        (Some(x), Some(y)) if y < x => format!("{x} -> {y} (improving)"),
+        (Some(x), Some(_)) => format!("steady {x}"),
+        _ => "unknown".to_string(),
+    };
+    let worst = series.iter().filter_map(|(_, m)| m.conditions.flight_category()).max();
+    match worst {
+        Some(w) if Some(w) > a.flight_category() && Some(w) > b.flight_category() => {
+            lines.push(format!("  Category:   {category}, {w} at worst"))
+        }
+        _ => lines.push(format!("  Category:   {category}")),
+    }
+    lines
+}
+
+/// One station on the weather board.
+#[derive(Serialize)]
+struct BoardRow {
+    station: String,
+    category: Option<FlightCategory>,
+    time: String,
+    wind: String,
+    ceiling_ft: Option<u32>,
+    visibility: String,
+    altimeter_inhg: Option<f64>,
+    raw: String,
+}
+
+impl BoardRow {
+    /// Decoded from the raw report, or from AviationWeather's own fields when it won't parse.
+    fn from_entry(station: &str, entry: &Value) -> BoardRow {
+        let raw = get_str_field(entry, "rawOb").unwrap_or_default();
+        if let Ok(m) = parse_metar(&raw) {
+            let c = &m.conditions;
+            return BoardRow {
+                station: station.to_string(),
+                category: c.flight_category(),
+                time: m.time.map(|t| t.to_string()).unwrap_or_default(),
+                wind: c.wind.as_ref().map(|w| w.to_string()).unwrap_or_default(),
+                ceiling_ft: c.ceiling_ft(),
+                visibility: c.visibility_text(),
+                altimeter_inhg: m.altimeter_inhg,
+                raw,
+            };
+        }
+        let wind = match (entry.get("wdir"), entry.get("wspd").and_then(|x| x.as_f64())) {
+            (Some(d), Some(s)) => match d.as_i64() {
+                Some(d) => format!("{:03}° {} kt", d, s.round() as i64),
+                None => format!("variable {} kt", s.round() as i64),
+            },
+            _ => String::new(),
+        };
+        let ceiling_ft = entry
+            .get("clouds")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|l| matches!(l.get("cover").and_then(|c| c.as_str()), Some("BKN" | "OVC" | "OVX")))
+            .filter_map(|l| l.get("base").and_then(|b| b.as_f64()))
+            .map(|b| b as u32)
+            .min();
+        BoardRow {
+            station: station.to_string(),
+            category: get_str_field(entry, "fltCat").and_then(|c| FlightCategory::from_awc(&c)),
+            time: entry
+                .get("obsTime")
+                .and_then(|n| n.as_i64())
+                .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+                .map(|t| t.format("%d%H%MZ").to_string())
+                .unwrap_or_default(),
+            wind,
+            ceiling_ft,
+            visibility: get_str_field(entry, "visib").map(|v| format!("{v} SM")).unwrap_or_default(),
+            // AviationWeather reports hPa
+            altimeter_inhg: entry
+                .get("altim")
+                .and_then(|a| a.as_f64())
+                .map(|a| if a >= 50.0 { a / HPA_PER_INHG } else { a }),
+            raw,
+        }
+    }
+}
+
+/// ICAO id for AviationWeather: three-letter US identifiers get a K prefix.
+fn icao_station(station: &str) -> String {
+    let st = station.trim().to_uppercase();
+    if st.len() == 3 && st.chars().all(|c| c.is_ascii_alphabetic()) {
+        format!("K{st}")
+    } else {
+        st
+    }
+}
+
+/// Flight-category board for a facility: one METAR request, worst conditions first.
+async fn handle_wxboard(
+    client: &reqwest::Client,
+    name: &str,
+    stations: &[String],
+    json: bool,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ids: Vec<String> = stations.iter().map(|s| icao_station(s)).collect();
+    let arr = into_vec(fetch_awc_cached(client, "metar", &ids.join(","), None, offline).await?);
+    let mut rows = Vec::new();
+    let mut missing = Vec::new();
+    for id in ids.iter() {
+        match arr.iter().find(|e| get_str_field(e, "icaoId").as_deref() == Some(id)) {
+            Some(entry) => rows.push(BoardRow::from_entry(id, entry)),
+            None => missing.push(id.clone()),
+        }
+    }
+    if rows.is_empty() {
+        eprintln!("No METARs for {} ({})", name.to_uppercase(), ids.join(", "));
+        return Ok(());
+    }
+    // worst category first, unknown last; ties keep the group's order
+    rows.sort_by_key(|r| std::cmp::Reverse(r.category));
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    if std::env::var_os("NO_COLOR").is_some() {
+        table.force_no_tty();
+    }
+    table.set_header(vec!["Station", "Cat", "Wind", "Ceiling", "Vis", "Alt", "Time"]);
+    for r in &rows {
+        let cat = match r.category {
+            Some(c) => Cell::new(c).fg(c.color()),
+            None => Cell::new("?"),
+        };
+        table.add_row(vec![
+            Cell::new(&r.station),
+            cat,
+            Cell::new(&r.wind),
+            Cell::new(r.ceiling_ft.map(|c| format!("{c} ft")).unwrap_or_else(|| "none".to_string())),
+            Cell::new(&r.visibility),
+            Cell::new(r.altimeter_inhg.map(|a| format!("{:.2}", a)).unwrap_or_default()),
+            Cell::new(&r.time),
+        ]);
+    }
+    println!("{table}");
+
+    let counts: Vec<String> = [
+        FlightCategory::Lifr,
+        FlightCategory::Ifr,
+        FlightCategory::Mvfr,
+        FlightCategory::Vfr,
+    ]
+    .into_iter()
+    .map(|c| format!("{} {}", rows.iter().filter(|r| r.category == Some(c)).count(), c))
+    .collect();
+    println!("{}: {} stations, {}", name.to_uppercase(), rows.len(), counts.join(", "));
+    if !missing.is_empty() {
+        eprintln!("No METAR for {}", missing.join(", "));
+    }
+    Ok(())
+}
+
+/// A report handed to `decode`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum DecodedReport {
+    Metar(Box<Metar>),
+    Taf(Taf),
+}
+
+/// Split pasted text into reports: TAF change groups and wrapped lines (indented, or
+/// starting with FM/TEMPO/BECMG/PROB/RMK) belong to the report above them.
+fn split_reports(input: &str) -> Vec<String> {
+    let mut reports: Vec<String> = Vec::new();
+    for line in input.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let continues = line.starts_with(char::is_whitespace)
+            || ["FM", "TEMPO", "BECMG", "PROB", "RMK"]
+                .iter()
+                .any(|p| trimmed.starts_with(p));
+        match reports.last_mut() {
+            Some(last) if continues => {
+                last.push(' ');
+                last.push_str(trimmed);
+            }
+            _ => reports.push(trimmed.to_string()),
+        }
+    }
+    reports
+}
+
+/// TAFs start with `TAF` or carry a `DDHH/DDHH` validity period right after the issue time.
+fn looks_like_taf(report: &str) -> bool {
+    let tokens: Vec<&str> = report.split_whitespace().take(5).collect();
+    tokens.first() == Some(&"TAF") || tokens.iter().any(|t| parse_taf_period(t).is_some())
+}
+
+/// Decode METARs/TAFs given on the command line, or pasted on stdin, without the network.
+fn handle_decode(text: &[String], json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let reports = if text.is_empty() || text == ["-"] {
+        split_reports(&std::io::read_to_string(std::io::stdin())?)
+    } else {
+        vec![text.join(" ")]
+    };
+    let mut decoded: Vec<DecodedReport> = Vec::new();
+    for report in reports.iter() {
+        let result = if looks_like_taf(report) {
+            parse_taf(report).map(DecodedReport::Taf)
+        } else {
+            parse_metar(report).map(|m| DecodedReport::Metar(Box::new(m)))
+        };
+        match result {
+            Ok(d) => decoded.push(d),
+            Err(e) => eprintln!("cannot decode '{}': {}", report, e),
+        }
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&decoded)?);
+        return Ok(());
+    }
+    for (i, d) in decoded.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        match d {
+            DecodedReport::Metar(m) => {
+                println!("{}", m.raw);
+                println!("{}", metar_detail_table(m));
+            }
+            DecodedReport::Taf(t) => {
+                println!("{}", t.raw);
+                let mut header = t.station.clone();
+                if let Some(issued) = t.issued {
+                    header.push_str(&format!("  issued: {}", issued));
+                }
+                if let (Some(f), Some(to)) = (t.valid_from, t.valid_to) {
+                    header.push_str(&format!("  valid: {} - {}", f, to));
+                }
+                if t.amended {
+                    header.push_str("  (amended)");
+                }
+                println!("{header}");
+                println!("{}", decoded_taf_table(t));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_taf(
+    client: &reqwest::Client,
+    station: &str,
+    raw: bool,
+    json: bool,
+    _verbose: bool,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut st = station.trim().to_uppercase();
+    let mut data = fetch_awc_cached(client, "taf", &st, None, offline).await?;
+    let mut arr = into_vec(data);
+    if arr.is_empty() && st.len() == 3 && !st.starts_with('K') {
+        st = format!("K{}", st);
+        data = fetch_awc_cached(client, "taf", &st, None, offline).await?;
+        arr = into_vec(data);
+    }
+    if arr.is_empty() {
+        if offline {
+            eprintln!("No cached TAF for {} (offline)", st);
+        } else {
+            eprintln!("No TAF data found for {}", st);
+        }
+        return Ok(());
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&arr)?);
+        return Ok(());
+    }
+    for entry in arr {
+        let rawtxt = get_str_field(&entry, "rawTAF").unwrap_or_default();
+        if !rawtxt.is_empty() {
+            println!("{}", rawtxt);
+            println!();
+        } else if raw {
+            println!("{}", serde_json::to_string_pretty(&entry)?);
+            println!();
+        }
+        let station_name = get_str_field(&entry, "icaoId").unwrap_or_else(|| st.clone());
+        let issue = get_str_field(&entry, "issueTime").unwrap_or_default();
+        let valid_from = entry
+            .get("validTimeFrom")
+            .and_then(|n| n.as_i64())
+            .map(format_unix)
+            .unwrap_or_default();
+        let valid_to = entry
+            .get("validTimeTo")
+            .and_then(|n| n.as_i64())
+            .map(format_unix)
+            .unwrap_or_default();
+        let header = format!(
+            "{}  issued: {}  valid: {} - {}",
+            station_name, issue, valid_from, valid_to
+        );
+        println!("{header}");
+        let taf_table = match parse_taf(&rawtxt) {
+            Ok(taf) => decoded_taf_table(&taf),
+            Err(_) => build_taf_table(&entry),
+        };
+        println!("{taf_table}");
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
+fn flatten_text(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.to_lowercase(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Array(a) => a.iter().map(flatten_text).collect::<Vec<_>>().join(" "),
+        Value::Object(o) => o.values().map(flatten_text).collect::<Vec<_>>().join(" "),
+        Value::Null => "".to_string(),
+    }
+}
+
+#[allow(dead_code)]
+fn find_first_pdf_url(v: &Value, base: &str) -> Option<String> {
+    // collect exact-URL candidates first, fallback to bare filenames
+    let mut full_urls: Vec<String> = Vec::new();
+    let mut file_names: Vec<String> = Vec::new();
+
+    fn rec(val: &Value, full_urls: &mut Vec<String>, file_names: &mut Vec<String>) {
+        match val {
+            Value::String(s) => {
+                let s = s.trim();
+                if !s.to_lowercase().contains(".pdf") {
+                    return;
+                }
+                if s.starts_with("http://")
+                    || s.starts_with("https://")
+                    || s.starts_with("file://")
+                    || s.starts_with("//")
+                {
+                    full_urls.push(s.to_string());
+                } else {
+                    file_names.push(s.to_string());
+                }
+            }
+            Value::Array(arr) => {
+                for it in arr {
+                    rec(it, full_urls, file_names);
+                }
+            }
+            Value::Object(map) => {
+                for (_k, v) in map {
+                    rec(v, full_urls, file_names);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    rec(v, &mut full_urls, &mut file_names);
+
+    // prefer absolute/complete URLs
+    if let Some(u) = full_urls.into_iter().next() {
+        if u.starts_with("//") {
+            return Some(format!("https:{}", u));
+        }
+        return Some(u);
+    }
+
+    // build an absolute URL from a bare filename or relative path
+    if let Some(name) = file_names.into_iter().next() {
+        let base = base.trim_end_matches('/');
+        // derive domain base (strip /v2 if present) so we can join leading '/...' paths sensibly
+        let domain_base = if let Some(pos) = base.find("/v2") {
+            &base[..pos]
+        } else {
+            base
+        };
+
+        if name.starts_with('/') {
+            // e.g. "/v2/charts/..." or "/files/..."
+            return Some(format!("{}{}", domain_base.trim_end_matches('/'), name));
+        }
+
+        // if we only have a filename, try sensible locations under the provided base
+        // prefer base/charts/<name> so we cover api-v2 hosting patterns
+        return Some(format!("{}/charts/{}", base, name));
+    }
+
+    None
+}
+
+/// Charts that can be opened or downloaded: everything but records of deleted charts.
+fn live_charts(charts: Vec<ChartInfo>) -> Vec<ChartInfo> {
+    charts.into_iter().filter(|c| !c.is_deleted()).collect()
+}
+
+/// Chart list for `airport` in the cycle selected by `opts`, from the cache when possible.
+/// Prints why and returns an empty list when nothing is available.
+async fn load_charts(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airport: &str,
+    opts: &ChartOptions,
+) -> Result<Vec<ChartInfo>, Box<dyn std::error::Error>> {
+    let ChartOptions {
+        airac,
+        refresh,
+        offline,
+        verbose,
+        ..
+    } = *opts;
+    // Only pin a cycle on the request when it differs from the one in effect, so the
+    // default lookup keeps hitting the API's current listing.
+    let current = AiracCycle::current();
+    let cycle = airac.filter(|c| *c != current);
+    let cache_cycle = airac.unwrap_or(current);
+    prune_chart_cache(&current);
+
+    let cached = if refresh && !offline {
+        None
+    } else {
+        load_cached_charts(&cache_cycle, airport).filter(|e| e.provider == provider.name())
+    };
+    let charts = if let Some(entry) = cached {
+        if offline {
+            print_stale_notice(
+                &format!("chart list {} (AIRAC {})", airport.to_uppercase(), entry.cycle),
+                entry.fetched_at,
+            );
+        } else if verbose {
+            eprintln!(
+                "using cached charts for {} (AIRAC {}, fetched {})",
+                airport.to_uppercase(),
+                entry.cycle,
+                format_unix(entry.fetched_at)
+            );
+        }
+        entry.charts
+    } else if offline {
+        eprintln!(
+            "No cached charts for {} in AIRAC {} (offline)",
+            airport.to_uppercase(),
+            cache_cycle
+        );
+        return Ok(Vec::new());
+    } else {
+        let charts = provider
+            .fetch_charts(client, airport, cycle.as_ref(), verbose)
+            .await?;
+        if !charts.is_empty()
+            && let Err(e) = store_cached_charts(provider.name(), &cache_cycle, airport, &charts)
+            && verbose
+        {
+            eprintln!("failed to write chart cache: {e}");
+        }
+        charts
+    };
+
+    if charts.is_empty() {
+        match &cycle {
+            Some(c) => eprintln!("No charts found for {} in AIRAC {}", airport, c),
+            None => eprintln!("No charts found for {}", airport),
+        }
+    }
+    Ok(charts)
+}
+
+/// Local copy of a chart PDF: downloaded into the cache, or only looked up there when offline.
+async fn local_pdf(
+    client: &reqwest::Client,
+    cycle: &AiracCycle,
+    airport: &str,
+    url: &str,
+    opts: &ChartOptions,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if opts.offline {
+        let path = cached_pdf_path(cycle, airport, url);
+        if !path.exists() {
+            return Err(format!("{} is not cached (offline)", url).into());
+        }
+        return Ok(path);
+    }
+    cached_pdf(client, cycle, airport, url, opts.refresh).await
+}
+
+/// Flags shared by the chart lookup paths.
+#[derive(Clone, Copy)]
+struct ChartOptions {
+    link_only: bool,
+    airac: Option<AiracCycle>,
+    refresh: bool,
+    offline: bool,
+    merge: bool,
+    auto_open: bool,
+    render: Option<RenderMode>,
+    verbose: bool,
+}
+
+/// Prompt for one of the ranked candidates. Returns `None` if the user cancels.
+fn pick_chart(matches: &[ChartMatch]) -> Result<Option<ChartInfo>, Box<dyn std::error::Error>> {
+    let shown = &matches[..matches.len().min(15)];
+    let mut table = Table::new();
+    table.set_header(vec!["#", "Score", "Type", "Chart"]);
+    for (i, m) in shown.iter().enumerate() {
+        table.add_row(vec![
+            (i + 1).to_string(),
+            format!("{:.2}", m.score),
+            m.chart.chart_type().to_string(),
+            m.chart.chart_name.clone(),
+        ]);
+    }
+    println!("Multiple possible charts:");
+    println!("{table}");
+    loop {
+        print!("Select chart [1-{}, Enter to cancel]: ", shown.len());
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() || line.eq_ignore_ascii_case("q") {
+            return Ok(None);
+        }
+        match line.parse::<usize>() {
+            Ok(n) if (1..=shown.len()).contains(&n) => return Ok(Some(shown[n - 1].chart.clone())),
+            _ => eprintln!("Enter a number between 1 and {}.", shown.len()),
+        }
+    }
+}
+
+/// Best match for `query` among `charts`, asking the user to pick when it's ambiguous.
+/// Prints the candidates and returns `None` when nothing could be chosen.
+fn resolve_chart(
+    provider: &dyn ChartProvider,
+    charts: &[ChartInfo],
+    airport: &str,
+    query: &[String],
+) -> Result<Option<ChartInfo>, Box<dyn std::error::Error>> {
+    let q_str = query.join(" ");
+    let cq = ChartQuery::new(airport, &q_str);
+    let (maybe_chart, matches) = find_chart_by_name(charts, &cq, 0.15);
+
+    let chart = match maybe_chart {
+        Some(c) => c,
+        None if !matches.is_empty()
+            && std::io::stdout().is_terminal()
+            && std::io::stdin().is_terminal() =>
+        {
+            match pick_chart(&matches)? {
+                Some(c) => c,
+                None => return Ok(None),
+            }
+        }
+        None => {
+            let mut table = comfy_table::Table::new();
+            table.set_header(vec!["Idx", "Title / Name", "Likely PDF"]);
+            for (i, ch) in charts.iter().take(12).enumerate() {
+                let pdf = provider.pdf_url(ch);
+                table.add_row(vec![
+                    i.to_string().as_str(),
+                    ch.chart_name.as_str(),
+                    pdf.as_str(),
+                ]);
+            }
+            println!("Multiple possible charts (no strong match).");
+            println!("{table}");
+            println!("Refine your query or pass a more specific string.");
+            return Ok(None);
+        }
+    };
+    Ok(Some(chart))
+}
+
+async fn handle_chart(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airport: &str,
+    query: &[String],
+    opts: &ChartOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ChartOptions { airac, verbose, .. } = *opts;
+    if verbose {
+        eprintln!("charts provider: {}", provider.describe());
+        eprintln!("airport arg: {}", airport);
+        eprintln!("query tokens: {:?}", query);
+        if let Some(c) = &airac {
+            eprintln!(
+                "airac: {} (effective {}, expires {})",
+                c,
+                c.effective,
+                c.expires()
+            );
+        }
+    }
+
+    let cache_cycle = airac.unwrap_or_else(AiracCycle::current);
+    let charts = live_charts(load_charts(client, provider, airport, opts).await?);
+    if charts.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(rwy) = runway_query(query) {
+        let approaches = approaches_for_runway(&charts, &rwy);
+        if !approaches.is_empty() {
+            let mut table = Table::new();
+            table.set_header(vec!["Approach", "PDF"]);
+            for c in approaches.iter() {
+                table.add_row(vec![c.chart_name.clone(), provider.pdf_url(c)]);
+            }
+            println!("{} RWY {} approaches ({})", airport.to_uppercase(), rwy, approaches.len());
+            println!("{table}");
+            return Ok(());
+        }
+        if verbose {
+            eprintln!("no approaches for RWY {}, falling back to name search", rwy);
+        }
+    }
+
+    let Some(chart) = resolve_chart(provider, &charts, airport, query)? else {
+        return Ok(());
+    };
+    if let Some(note) = chart.amendment_note() {
+        eprintln!("{}: {}", chart.chart_name, note);
+    }
+    let pages = find_all_chart_pages(&charts, &chart);
+    let pdf_urls: Vec<String> =
+        pages.iter().map(|p| provider.pdf_url(p)).collect();
+    record_chart_lookup(airport, query, &chart.chart_name, &cache_cycle, &pdf_urls, verbose);
+    show_chart(client, airport, &chart.chart_name, &pdf_urls, opts).await
+}
+
+/// Render, merge, print or open the resolved pages of `chart_name`, as `opts` asks.
+async fn show_chart(
+    client: &reqwest::Client,
+    airport: &str,
+    chart_name: &str,
+    pdf_urls: &[String],
+    opts: &ChartOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ChartOptions {
+        link_only,
+        airac,
+        refresh,
+        offline,
+        merge,
+        auto_open,
+        render,
+        verbose,
+    } = *opts;
+    let cache_cycle = airac.unwrap_or_else(AiracCycle::current);
+
+    if let Some(mode) = render
+        && !link_only
+    {
+        let mode = mode.resolve();
+        if verbose {
+            eprintln!("rendering {} page(s) as {:?}", pdf_urls.len(), mode);
+        }
+        for u in pdf_urls.iter() {
+            let path = local_pdf(client, &cache_cycle, airport, u, opts).await?;
+            if let Err(e) = render_pdf(&path, mode) {
+                // a closed pipe is not a rendering problem; only fall back when drawing failed
+                if mode == RenderMode::Text || e.is::<std::io::Error>() {
+                    return Err(e);
+                }
+                eprintln!("failed to draw {}: {e}; showing text instead", path.display());
+                render_pdf(&path, RenderMode::Text)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if merge && !link_only && pdf_urls.len() > 1 {
+        let out = chart_cache_dir(&cache_cycle, airport)
+            .join("merged")
+            .join(pdf_file_name(chart_name));
+        if !out.exists() || refresh {
+            let mut paths = Vec::with_capacity(pdf_urls.len());
+            for u in pdf_urls.iter() {
+                paths.push(local_pdf(client, &cache_cycle, airport, u, opts).await?);
+            }
+            merge_pdfs(&paths, &out)?;
+        }
+        let target = out.to_string_lossy().to_string();
+        if !auto_open {
+            println!("{}", target);
+            return Ok(());
+        }
+        if verbose {
+            eprintln!("Opening merged {} ({} pages)", target, pdf_urls.len());
+        }
+        if let Err(e) = open::that(&target) {
+            eprintln!("failed to open: {e}");
+            println!("{}", target);
+        }
+        return Ok(());
+    }
+
+    if link_only || !auto_open {
+        for u in pdf_urls.iter() {
+            println!("{}", u);
+        }
+        return Ok(());
+    }
+
+    if let Some(url) = pdf_urls.first() {
+        let local = if offline {
+            let path = cached_pdf_path(&cache_cycle, airport, url);
+            if !path.exists() {
+                eprintln!("PDF not cached (offline):");
+                for u in pdf_urls.iter() {
+                    println!("{}", u);
+                }
+                return Ok(());
+            }
+            Ok(path)
+        } else {
+            cached_pdf(client, &cache_cycle, airport, url, refresh).await
+        };
+        let target = match local {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(e) => {
+                if verbose {
+                    eprintln!("pdf cache miss ({e}), opening remote URL");
+                }
+                url.clone()
+            }
+        };
+        if verbose {
+            eprintln!("Opening {}", target);
+        }
+        match open::that(&target) {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                eprintln!("failed to open: {e}");
+                println!("{}", url);
+                for u in pdf_urls.iter().skip(1) {
+                    println!("{}", u);
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_chart_text(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airport: &str,
+    query: &[String],
+    opts: &ChartOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cycle = opts.airac.unwrap_or_else(AiracCycle::current);
+    let charts = live_charts(load_charts(client, provider, airport, opts).await?);
+    if charts.is_empty() {
+        return Ok(());
+    }
+    let Some(chart) = resolve_chart(provider, &charts, airport, query)? else {
+        return Ok(());
+    };
+    if let Some(note) = chart.amendment_note() {
+        eprintln!("{}: {}", chart.chart_name, note);
+    }
+    let pages = find_all_chart_pages(&charts, &chart);
+    let pdf_urls: Vec<String> = pages.iter().map(|p| provider.pdf_url(p)).collect();
+    for (page, url) in pages.iter().zip(pdf_urls.iter()) {
+        let path = local_pdf(client, &cycle, airport, url, opts).await?;
+        let text = pdf_text(&path)?;
+        if pages.len() > 1 {
+            println!("== {} ==", page.chart_name);
+        }
+        if text.trim().is_empty() {
+            eprintln!("{} has no text layer (scanned chart?)", page.chart_name);
+            continue;
+        }
+        println!("{}", text.trim_end());
+    }
+    record_chart_lookup(airport, query, &chart.chart_name, &cycle, &pdf_urls, opts.verbose);
+    Ok(())
+}
+
+/// Lookups kept in `history.json`: pinned favorites plus the most recently opened charts.
+#[derive(Default, Deserialize, Serialize)]
+struct ChartHistory {
+    #[serde(default)]
+    pins: Vec<PinnedChart>,
+    #[serde(default)]
+    recent: Vec<ChartRef>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+struct PinnedChart {
+    alias: String,
+    #[serde(flatten)]
+    chart: ChartRef,
+}
+
+/// A resolved chart and the page PDFs it resolved to in recent AIRAC cycles.
+#[derive(Clone, Deserialize, Serialize)]
+struct ChartRef {
+    airport: String,
+    query: String,
+    chart_name: String,
+    /// AIRAC ident -> PDF URL of each page, in order.
+    #[serde(default)]
+    pdfs: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    used_at: i64,
+}
+
+/// Entries kept in the recent list.
+const RECENT_CHARTS: usize = 20;
+/// Cycles of resolved PDFs kept per chart (current plus the one before or after).
+const CHART_REF_CYCLES: usize = 2;
+
+impl ChartRef {
+    fn new(airport: &str, query: &[String], chart_name: &str) -> ChartRef {
+        ChartRef {
+            airport: airport.trim().to_uppercase(),
+            query: query.join(" "),
+            chart_name: chart_name.to_string(),
+            pdfs: BTreeMap::new(),
+            used_at: 0,
+        }
+    }
+
+    fn is(&self, airport: &str, chart_name: &str) -> bool {
+        self.airport.eq_ignore_ascii_case(airport.trim()) && self.chart_name == chart_name
+    }
+
+    fn remember(&mut self, cycle: &AiracCycle, pdf_urls: &[String]) {
+        self.pdfs.insert(cycle.ident(), pdf_urls.to_vec());
+        while self.pdfs.len() > CHART_REF_CYCLES {
+            self.pdfs.pop_first();
+        }
+        self.used_at = Utc::now().timestamp();
+    }
+}
+
+fn history_path() -> PathBuf {
+    if let Ok(p) = std::env::var("ZDC_HISTORY") {
+        return PathBuf::from(p);
+    }
+    config_path()
+        .parent()
+        .map(|d| d.join("history.json"))
+        .unwrap_or_else(|| PathBuf::from("history.json"))
+}
+
+impl ChartHistory {
+    fn load() -> ChartHistory {
+        fs::read_to_string(history_path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = history_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// A pin by alias, or a recent entry by its 1-based index.
+    fn find(&self, target: &str) -> Option<&ChartRef> {
+        if let Ok(n) = target.parse::<usize>() {
+            return n.checked_sub(1).and_then(|i| self.recent.get(i));
+        }
+        self.pins
+            .iter()
+            .find(|p| p.alias.eq_ignore_ascii_case(target.trim()))
+            .map(|p| &p.chart)
+    }
+
+    /// Move a resolved lookup to the front of the recent list and refresh any pin for it.
+    fn record(
+        &mut self,
+        airport: &str,
+        query: &[String],
+        chart_name: &str,
+        cycle: &AiracCycle,
+        pdf_urls: &[String],
+    ) {
+        let mut entry = match self.recent.iter().position(|r| r.is(airport, chart_name)) {
+            Some(i) => self.recent.remove(i),
+            None => ChartRef::new(airport, query, chart_name),
+        };
+        if !query.is_empty() {
+            entry.query = query.join(" ");
+        }
+        entry.remember(cycle, pdf_urls);
+        self.recent.insert(0, entry);
+        self.recent.truncate(RECENT_CHARTS);
+        for pin in self.pins.iter_mut().filter(|p| p.chart.is(airport, chart_name)) {
+            pin.chart.remember(cycle, pdf_urls);
+        }
+    }
+}
+
+/// Remember a resolved lookup in `history.json`.
+fn record_chart_lookup(
+    airport: &str,
+    query: &[String],
+    chart_name: &str,
+    cycle: &AiracCycle,
+    pdf_urls: &[String],
+    verbose: bool,
+) {
+    let mut history = ChartHistory::load();
+    history.record(airport, query, chart_name, cycle, pdf_urls);
+    if let Err(e) = history.save()
+        && verbose
+    {
+        eprintln!("failed to write chart history: {e}");
+    }
+}
+
+async fn handle_chart_pin(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airport: &str,
+    query: &[String],
+    alias: Option<String>,
+    opts: &ChartOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let alias = alias
+        .unwrap_or_else(|| format!("{}-{}", airport.trim(), query.concat()))
+        .to_lowercase();
+    if alias.parse::<usize>().is_ok() {
+        return Err(format!("pin alias '{}' can't be a number (numbers pick recent charts)", alias).into());
+    }
+    let cycle = opts.airac.unwrap_or_else(AiracCycle::current);
+    let charts = live_charts(load_charts(client, provider, airport, opts).await?);
+    if charts.is_empty() {
+        return Ok(());
+    }
+    let Some(chart) = resolve_chart(provider, &charts, airport, query)? else {
+        return Ok(());
+    };
+    let pdf_urls: Vec<String> = find_all_chart_pages(&charts, &chart)
+        .iter()
+        .map(|p| provider.pdf_url(p))
+        .collect();
+
+    let mut entry = ChartRef::new(airport, query, &chart.chart_name);
+    entry.remember(&cycle, &pdf_urls);
+    let mut history = ChartHistory::load();
+    history.pins.retain(|p| p.alias != alias);
+    history.pins.push(PinnedChart {
+        alias: alias.clone(),
+        chart: entry,
+    });
+    history.save()?;
+    println!(
+        "Pinned {} {} as '{}' (open with `zdc chart {}`)",
+        airport.to_uppercase(),
+        chart.chart_name,
+        alias,
+        alias
+    );
+    Ok(())
+}
+
+fn handle_chart_unpin(alias: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut history = ChartHistory::load();
+    let before = history.pins.len();
+    history.pins.retain(|p| !p.alias.eq_ignore_ascii_case(alias.trim()));
+    if history.pins.len() == before {
+        return Err(format!("no pinned chart named '{}'", alias).into());
+    }
+    history.save()?;
+    println!("Unpinned '{}'", alias.to_lowercase());
+    Ok(())
+}
+
+fn handle_chart_recent(limit: usize) {
+    let history = ChartHistory::load();
+    if history.pins.is_empty() && history.recent.is_empty() {
+        println!("No pinned or recent charts yet.");
+        return;
+    }
+    let cycles = |r: &ChartRef| r.pdfs.keys().cloned().collect::<Vec<_>>().join(", ");
+    if !history.pins.is_empty() {
+        let mut table = Table::new();
+        table.set_header(vec!["Pin", "Airport", "Chart", "Cycles"]);
+        for p in history.pins.iter() {
+            table.add_row(vec![
+                p.alias.clone(),
+                p.chart.airport.clone(),
+                p.chart.chart_name.clone(),
+                cycles(&p.chart),
+            ]);
+        }
+        println!("Pinned");
+        println!("{table}");
+    }
+    if !history.recent.is_empty() {
+        let mut table = Table::new();
+        table.set_header(vec!["#", "Airport", "Chart", "Query", "Opened"]);
+        for (i, r) in history.recent.iter().take(limit).enumerate() {
+            table.add_row(vec![
+                (i + 1).to_string(),
+                r.airport.clone(),
+                r.chart_name.clone(),
+                r.query.clone(),
+                format_unix(r.used_at),
+            ]);
+        }
+        println!("Recent");
+        println!("{table}");
+    }
+    println!("Open one with `zdc chart <PIN>` or `zdc chart <#>`.");
+}
+
+/// Open a pinned or recent chart, reusing the PDFs it resolved to when the cycle matches.
+async fn handle_chart_reopen(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    entry: &ChartRef,
+    opts: &ChartOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cycle = opts.airac.unwrap_or_else(AiracCycle::current);
+    let airport = entry.airport.as_str();
+    let query: Vec<String> = entry.query.split_whitespace().map(String::from).collect();
+    let (chart_name, pdf_urls) = match entry.pdfs.get(&cycle.ident()) {
+        Some(urls) if !opts.refresh => {
+            if opts.verbose {
+                eprintln!("using stored pages for {} (AIRAC {})", entry.chart_name, cycle);
+            }
+            (entry.chart_name.clone(), urls.clone())
+        }
+        _ => {
+            let charts = live_charts(load_charts(client, provider, airport, opts).await?);
+            if charts.is_empty() {
+                return Ok(());
+            }
+            // same title first; fall back to the original query in case the procedure was renumbered
+            let chart = charts
+                .iter()
+                .find(|c| c.chart_name.eq_ignore_ascii_case(&entry.chart_name))
+                .cloned()
+                .or_else(|| find_chart_by_name(&charts, &ChartQuery::new(airport, &entry.query), 0.15).0);
+            let Some(chart) = chart else {
+                eprintln!(
+                    "{} {} is not in AIRAC {}; try `zdc chart {} {}`",
+                    airport, entry.chart_name, cycle, airport, entry.query
+                );
+                return Ok(());
+            };
+            if chart.chart_name != entry.chart_name {
+                eprintln!("{} is now {}", entry.chart_name, chart.chart_name);
+            }
+            let urls = find_all_chart_pages(&charts, &chart)
+                .iter()
+                .map(|p| provider.pdf_url(p))
+                .collect();
+            (chart.chart_name, urls)
+        }
+    };
+    record_chart_lookup(airport, &query, &chart_name, &cycle, &pdf_urls, opts.verbose);
+    show_chart(client, airport, &chart_name, &pdf_urls, opts).await
+}
+
+#[derive(Default)]
+struct SyncStats {
+    charts: usize,
+    added: Vec<String>,
+    removed: Vec<String>,
+    downloaded: usize,
+    cached: usize,
+    failed: usize,
+}
+
+/// Each airport's listing to compare a sync against: the synced cycle's, else the previous one's.
+fn sync_baselines(root: &Path, cycle: &AiracCycle, airports: &[String]) -> Vec<Option<Vec<ChartInfo>>> {
+    airports
+        .iter()
+        .map(|ap| {
+            read_chart_cache(root, cycle, ap)
+                .or_else(|| read_chart_cache(root, &cycle.previous(), ap))
+                .map(|e| e.charts)
+        })
+        .collect()
+}
+
+async fn handle_chart_sync(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airports: &[String],
+    cycle: AiracCycle,
+    jobs: usize,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let opts = ChartOptions {
+        link_only: true,
+        airac: Some(cycle),
+        refresh: true,
+        offline: false,
+        merge: false,
+        auto_open: false,
+        render: None,
+        verbose,
+    };
+
+    let mut seen = HashSet::new();
+    let airports: Vec<String> = airports
+        .iter()
+        .filter(|a| seen.insert(a.to_string()))
+        .cloned()
+        .collect();
+
+    // Read every baseline before fetching anything: the first fetch prunes expired cycles,
+    // which on rollover day would take the previous listing away from the airports after it.
+    let baselines = sync_baselines(&cache_root().join("charts"), &cycle, &airports);
+    let mut stats: Vec<SyncStats> = Vec::new();
+    let mut downloads: Vec<(usize, String)> = Vec::new();
+    for (idx, (ap, before)) in airports.iter().zip(baselines).enumerate() {
+        let charts = match load_charts(client, provider, ap, &opts).await {
+            Ok(c) => live_charts(c),
+            Err(e) => {
+                eprintln!("{}: failed to fetch chart list: {e}", ap);
+                Vec::new()
+            }
+        };
+        let mut st = SyncStats {
+            charts: charts.len(),
+            ..Default::default()
+        };
+        if let Some(before) = before {
+            let old: BTreeSet<&str> = before
+                .iter()
+                .filter(|c| !c.is_deleted())
+                .map(|c| c.chart_name.as_str())
+                .collect();
+            let new: BTreeSet<&str> = charts.iter().map(|c| c.chart_name.as_str()).collect();
+            st.added = new.difference(&old).map(|s| s.to_string()).collect();
+            st.removed = old.difference(&new).map(|s| s.to_string()).collect();
+        }
+        let mut urls = HashSet::new();
+        for c in charts.iter().filter(|c| !c.pdf_path.trim().is_empty()) {
+            let url = provider.pdf_url(c);
+            if urls.insert(url.clone()) {
+                downloads.push((idx, url));
+            }
+        }
+        stats.push(st);
+    }
+
+    if verbose {
+        eprintln!(
+            "syncing {} PDFs for AIRAC {} with {} parallel downloads",
+            downloads.len(),
+            cycle,
+            jobs.max(1)
+        );
+    }
+
+    let results: Vec<(usize, bool, Result<PathBuf, String>)> = stream::iter(downloads)
+        .map(|(idx, url)| {
+            let ap = airports[idx].clone();
+            async move {
+                let existed = cached_pdf_path(&cycle, &ap, &url).exists();
+                let res = cached_pdf(client, &cycle, &ap, &url, false)
+                    .await
+                    .map_err(|e| e.to_string());
+                (idx, existed, res)
+            }
+        })
+        .buffer_unordered(jobs.max(1))
+        .collect()
+        .await;
+
+    for (idx, existed, res) in results {
+        let st = &mut stats[idx];
+        match res {
+            Ok(_) if existed => st.cached += 1,
+            Ok(_) => st.downloaded += 1,
+            Err(e) => {
+                st.failed += 1;
+                eprintln!("{}: {}", airports[idx], e);
+            }
+        }
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Airport", "Charts", "Added", "Removed", "Downloaded", "Cached", "Failed",
+    ]);
+    for (ap, st) in airports.iter().zip(stats.iter()) {
+        table.add_row(vec![
+            ap.clone(),
+            st.charts.to_string(),
+            st.added.len().to_string(),
+            st.removed.len().to_string(),
+            st.downloaded.to_string(),
+            st.cached.to_string(),
+            st.failed.to_string(),
+        ]);
+    }
+    println!("AIRAC {} chart sync -> {}", cycle, cache_root().join("charts").join(cycle.ident()).display());
+    println!("{table}");
+
+    for (ap, st) in airports.iter().zip(stats.iter()) {
+        for name in &st.added {
+            println!("  + {} {}", ap, name);
+        }
+        for name in &st.removed {
+            println!("  - {} {}", ap, name);
+        }
+    }
+    Ok(())
+}
+
+async fn handle_chart_search(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airports: &[String],
+    query: &[String],
+    limit: usize,
+    opts: &ChartOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // -n 0 still shows the best match
+    let limit = limit.max(1);
+    let q_str = query.join(" ");
+    let per_airport: Vec<Vec<(String, ChartMatch)>> = stream::iter(airports.iter())
+        .map(|ap| {
+            let q_str = q_str.as_str();
+            async move {
+                let charts = match load_charts(client, provider, ap, opts).await {
+                    Ok(c) => live_charts(c),
+                    Err(e) => {
+                        eprintln!("{}: {}", ap, e);
+                        return Vec::new();
+                    }
+                };
+                let cq = ChartQuery::new(ap, q_str);
+                let (_, matches) = find_chart_by_name(&charts, &cq, 0.15);
+                matches.into_iter().map(|m| (ap.clone(), m)).collect()
+            }
+        })
+        .buffer_unordered(8)
+        .collect()
+        .await;
+
+    let mut results: Vec<(String, ChartMatch)> = per_airport.into_iter().flatten().collect();
+    results.sort_by(|a, b| {
+        b.1.score
+            .partial_cmp(&a.1.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
+    if results.is_empty() {
+        println!("No charts matching '{}' at {}", q_str, airports.join(", "));
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["#", "Airport", "Score", "Type", "Chart", "PDF"]);
+    for (i, (ap, m)) in results.iter().take(limit).enumerate() {
+        table.add_row(vec![
+            (i + 1).to_string(),
+            ap.clone(),
+            format!("{:.2}", m.score),
+            m.chart.chart_type().to_string(),
+            m.chart.chart_name.clone(),
+            provider.pdf_url(&m.chart),
+        ]);
+    }
+    println!("{table}");
+    if results.len() > limit {
+        println!("{} more; raise --limit to see them.", results.len() - limit);
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ListFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+struct ChartListFilter {
+    types: Vec<ChartType>,
+    changed: bool,
+}
+
+async fn handle_chart_list(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airport: &str,
+    filter: &ChartListFilter,
+    format: ListFormat,
+    opts: &ChartOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let charts = load_charts(client, provider, airport, opts).await?;
+    if charts.is_empty() {
+        return Ok(());
+    }
+    let mut rows: Vec<(ChartType, &ChartInfo, String)> = charts
+        .iter()
+        .map(|c| (c.chart_type(), c, provider.pdf_url(c)))
+        .filter(|(t, _, _)| filter.types.is_empty() || filter.types.contains(t))
+        .filter(|(_, c, _)| !filter.changed || c.user_action.is_some())
+        .collect();
+    let has_amendments = rows
+        .iter()
+        .any(|(_, c, _)| c.amendment.is_some() || c.user_action.is_some());
+    // stable sort keeps the API's order within each group, so ", CONT." pages follow their chart
+    rows.sort_by_key(|(t, _, _)| *t);
+
+    match format {
+        ListFormat::Json => {
+            let out: Vec<Value> = rows
+                .iter()
+                .map(|(t, c, url)| {
+                    serde_json::json!({
+                        "type": t.to_string(),
+                        "chart_name": c.chart_name,
+                        "chart_code": c.chart_code,
+                        "pdf_url": url,
+                        "amendment": c.amendment,
+                        "amendment_date": c.amendment_date,
+                        "user_action": c.user_action,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&out)?);
+        }
+        ListFormat::Csv => {
+            println!("type,chart_name,chart_code,pdf_url,amendment,amendment_date,user_action");
+            for (t, c, url) in rows.iter() {
+                println!(
+                    "{},{},{},{},{},{},{}",
+                    t,
+                    csv_field(&c.chart_name),
+                    csv_field(&c.chart_code),
+                    csv_field(url),
+                    csv_field(c.amendment.as_deref().unwrap_or("")),
+                    csv_field(c.amendment_date.as_deref().unwrap_or("")),
+                    c.user_action.map(|u| u.to_string()).unwrap_or_default()
+                );
+            }
+        }
+        ListFormat::Table => {
+            if rows.is_empty() {
+                println!("No charts of the requested type for {}", airport.to_uppercase());
+                return Ok(());
+            }
+            let mut current: Option<ChartType> = None;
+            let mut table = Table::new();
+            for (t, c, url) in rows.iter() {
+                if current != Some(*t) {
+                    if current.is_some() {
+                        println!("{table}");
+                        println!();
+                    }
+                    let count = rows.iter().filter(|(rt, _, _)| rt == t).count();
+                    println!("{} ({})", t, count);
+                    table = Table::new();
+                    if has_amendments {
+                        table.set_header(vec!["Name", "Code", "Amdt", "Action", "URL"]);
+                    } else {
+                        table.set_header(vec!["Name", "Code", "URL"]);
+                    }
+                    current = Some(*t);
+                }
+                if has_amendments {
+                    let amdt = match (&c.amendment, &c.amendment_date) {
+                        (Some(a), Some(d)) => format!("{} ({})", a, d),
+                        (Some(a), None) => a.clone(),
+                        _ => String::new(),
+                    };
+                    let action = c.user_action.map(|u| u.to_string()).unwrap_or_default();
+                    table.add_row(vec![
+                        c.chart_name.as_str(),
+                        c.chart_code.as_str(),
+                        amdt.as_str(),
+                        action.as_str(),
+                        url.as_str(),
+                    ]);
+                } else {
+                    table.add_row(vec![c.chart_name.as_str(), c.chart_code.as_str(), url.as_str()]);
+                }
+            }
+            println!("{table}");
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum ChartChangeKind {
+    Added,
+    Removed,
+    Renamed,
+    Amended,
+}
+
+impl std::fmt::Display for ChartChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ChartChangeKind::Added => "added",
+            ChartChangeKind::Removed => "removed",
+            ChartChangeKind::Renamed => "renamed",
+            ChartChangeKind::Amended => "amended",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+struct ChartChange {
+    kind: ChartChangeKind,
+    chart: ChartInfo,
+    detail: String,
+}
 
-            let alt = f.get("altim").and_then(|n| n.as_f64()).map(|a| {
-                if a >= 50.0 {
-                    let inhg = a * 0.029529983071445;
-                    format!("{:.1} hPa ({:.2} inHg)", a, inhg)
-                } else {
-                    let hpa = a / 0.029529983071445;
-                    format!("{:.2} inHg ({:.1} hPa)", a, hpa)
+/// Pair up two chart lists by name, then by PDF file name, and classify the differences.
+/// `hash_of` returns the content hash for a chart's PDF when one is available.
+fn diff_chart_lists<F>(from: &[ChartInfo], to: &[ChartInfo], hash_of: F) -> Vec<ChartChange>
+where
+    F: Fn(&ChartInfo, bool) -> Option<String>,
+{
+    let from: Vec<&ChartInfo> = from.iter().filter(|c| !c.chart_name.is_empty()).collect();
+    let to: Vec<&ChartInfo> = to.iter().filter(|c| !c.chart_name.is_empty()).collect();
+    let mut used_from = vec![false; from.len()];
+    let mut used_to = vec![false; to.len()];
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+
+    for (j, b) in to.iter().enumerate() {
+        if let Some(i) = (0..from.len()).find(|&i| !used_from[i] && from[i].chart_name == b.chart_name) {
+            used_from[i] = true;
+            used_to[j] = true;
+            pairs.push((i, j));
+        }
+    }
+
+    let mut out = Vec::new();
+    for (j, b) in to.iter().enumerate() {
+        if used_to[j] || b.pdf_path.trim().is_empty() {
+            continue;
+        }
+        let file = pdf_file_name(&b.pdf_path).to_lowercase();
+        if let Some(i) = (0..from.len()).find(|&i| {
+            !used_from[i] && pdf_file_name(&from[i].pdf_path).to_lowercase() == file
+        }) {
+            used_from[i] = true;
+            used_to[j] = true;
+            out.push(ChartChange {
+                kind: ChartChangeKind::Renamed,
+                chart: (*b).clone(),
+                detail: format!("was {}", from[i].chart_name),
+            });
+        }
+    }
+
+    for (i, j) in pairs {
+        let (a, b) = (from[i], to[j]);
+        let mut notes = Vec::new();
+        if !a.chart_code.eq_ignore_ascii_case(&b.chart_code) {
+            notes.push(format!("code {} -> {}", a.chart_code, b.chart_code));
+        }
+        if let (Some(x), Some(y)) = (&a.amendment, &b.amendment)
+            && x != y
+        {
+            notes.push(format!("Amdt {} -> {}", x, y));
+        }
+        if let (Some(ha), Some(hb)) = (hash_of(a, false), hash_of(b, true))
+            && ha != hb
+        {
+            notes.push("PDF changed".to_string());
+        }
+        if !notes.is_empty() {
+            out.push(ChartChange {
+                kind: ChartChangeKind::Amended,
+                chart: b.clone(),
+                detail: notes.join(", "),
+            });
+        }
+    }
+
+    for (j, b) in to.iter().enumerate() {
+        if !used_to[j] {
+            out.push(ChartChange {
+                kind: ChartChangeKind::Added,
+                chart: (*b).clone(),
+                detail: String::new(),
+            });
+        }
+    }
+    for (i, a) in from.iter().enumerate() {
+        if !used_from[i] {
+            out.push(ChartChange {
+                kind: ChartChangeKind::Removed,
+                chart: (*a).clone(),
+                detail: String::new(),
+            });
+        }
+    }
+    out.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then_with(|| a.chart.chart_name.cmp(&b.chart.chart_name))
+    });
+    out
+}
+
+fn sha256_file(path: &PathBuf) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    let digest = Sha256::digest(&bytes);
+    Some(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Hash the PDFs behind `urls`, downloading any that are not yet cached (or only reading
+/// the cache when offline). URLs that cannot be fetched are left out.
+async fn pdf_hashes(
+    client: &reqwest::Client,
+    cycle: &AiracCycle,
+    airport: &str,
+    urls: Vec<String>,
+    jobs: usize,
+    offline: bool,
+) -> HashMap<String, String> {
+    stream::iter(urls)
+        .map(|url| async move {
+            let path = if offline {
+                Some(cached_pdf_path(cycle, airport, &url))
+            } else {
+                cached_pdf(client, cycle, airport, &url, false).await.ok()
+            };
+            let hash = path.and_then(|p| sha256_file(&p));
+            (url, hash)
+        })
+        .buffer_unordered(jobs.max(1))
+        .filter_map(|(url, hash)| async move { hash.map(|h| (url, h)) })
+        .collect()
+        .await
+}
+
+#[derive(Clone, Copy)]
+struct ChartDiffOptions {
+    from: AiracCycle,
+    to: AiracCycle,
+    hash: bool,
+    jobs: usize,
+    offline: bool,
+    verbose: bool,
+}
+
+async fn handle_chart_diff(
+    client: &reqwest::Client,
+    provider: &dyn ChartProvider,
+    airports: &[String],
+    opts: &ChartDiffOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let list_opts = |cycle: AiracCycle| ChartOptions {
+        link_only: true,
+        airac: Some(cycle),
+        refresh: false,
+        offline: opts.offline,
+        merge: false,
+        auto_open: false,
+        render: None,
+        verbose: opts.verbose,
+    };
+
+    let mut table = Table::new();
+    table.set_header(vec!["Airport", "Change", "Type", "Chart", "Detail"]);
+    let mut counts: HashMap<ChartChangeKind, usize> = HashMap::new();
+    let mut skipped: Vec<String> = Vec::new();
+    let mut compared = 0;
+    let current = AiracCycle::current();
+    for ap in airports {
+        let mut lists = Vec::new();
+        for cycle in [opts.from, opts.to] {
+            match load_charts(client, provider, ap, &list_opts(cycle)).await {
+                Ok(charts) if charts.is_empty() => {
+                    let hint = if cycle > current { ", not published yet?" } else { "" };
+                    skipped.push(format!("{}: no charts for AIRAC {}{}", ap, cycle, hint));
+                    break;
                 }
-            }).unwrap_or_default();
+                // a chart d-TPP flags as deleted is gone from that cycle
+                Ok(charts) => lists.push(live_charts(charts)),
+                Err(e) => {
+                    skipped.push(format!("{}: AIRAC {}: {}", ap, cycle, e));
+                    break;
+                }
+            }
+        }
+        let Ok([from, to]) = <[Vec<ChartInfo>; 2]>::try_from(lists) else {
+            continue;
+        };
+        compared += 1;
 
-            let clouds = f.get("clouds").and_then(|c| {
-                c.as_array().map(|arr| {
-                    arr.iter()
-                        .map(|layer| {
-                            let cover = layer.get("cover").and_then(|s| s.as_str()).unwrap_or("");
-                            let base = layer
-                                .get("base")
-                                .and_then(|n| n.as_i64().map(|b| b.to_string()))
-                                .or_else(|| {
-                                    layer
-                                        .get("base")
-                                        .and_then(|n| n.as_f64().map(|f| f.to_string()))
-                                })
-                                .unwrap_or_default();
-                            if base.is_empty() {
-                                cover.to_string()
-                            } else {
-                                format!("{}{}", cover, base)
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                })
-            }).unwrap_or_default();
+        let (mut from_hashes, mut to_hashes) = (HashMap::new(), HashMap::new());
+        if opts.hash {
+            let urls = |charts: &[ChartInfo]| -> Vec<String> {
+                charts
+                    .iter()
+                    .filter(|c| !c.pdf_path.trim().is_empty())
+                    .map(|c| provider.pdf_url(c))
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect()
+            };
+            from_hashes = pdf_hashes(client, &opts.from, ap, urls(&from), opts.jobs, opts.offline).await;
+            to_hashes = pdf_hashes(client, &opts.to, ap, urls(&to), opts.jobs, opts.offline).await;
+        }
+        let hash_of = |c: &ChartInfo, is_to: bool| {
+            let map = if is_to { &to_hashes } else { &from_hashes };
+            map.get(&provider.pdf_url(c)).cloned()
+        };
+
+        for change in diff_chart_lists(&from, &to, hash_of) {
+            *counts.entry(change.kind).or_default() += 1;
+            table.add_row(vec![
+                ap.clone(),
+                change.kind.to_string(),
+                change.chart.chart_code.clone(),
+                change.chart.chart_name.clone(),
+                change.detail,
+            ]);
+        }
+    }
+
+    if !skipped.is_empty() {
+        eprintln!("Skipped {} of {} airports:", skipped.len(), airports.len());
+        for s in skipped.iter() {
+            eprintln!("  {s}");
+        }
+    }
+    if compared == 0 {
+        return Err(format!(
+            "nothing to compare: no airport has charts for both AIRAC {} and {}",
+            opts.from, opts.to
+        )
+        .into());
+    }
+    println!("Chart changes AIRAC {} -> {}", opts.from, opts.to);
+    if counts.is_empty() {
+        println!("No changes.");
+        return Ok(());
+    }
+    println!("{table}");
+    let summary: Vec<String> = [
+        ChartChangeKind::Added,
+        ChartChangeKind::Removed,
+        ChartChangeKind::Renamed,
+        ChartChangeKind::Amended,
+    ]
+    .iter()
+    .map(|k| format!("{} {}", counts.get(k).copied().unwrap_or(0), k))
+    .collect();
+    println!("{}", summary.join(", "));
+    Ok(())
+}
+
+fn handle_airac(cycle: Option<AiracCycle>, count: usize) {
+    let today = Utc::now().date_naive();
+    let current = AiracCycle::current();
+    let cycle = cycle.unwrap_or(current);
+
+    let status = if cycle == current {
+        format!("current, {} days remaining", cycle.days_remaining(today))
+    } else if cycle > current {
+        format!("effective in {} days", (cycle.effective - today).num_days())
+    } else {
+        "expired".to_string()
+    };
+    println!("AIRAC {} ({})", cycle, status);
+    println!("  effective: {}", cycle.effective.format("%Y-%m-%d"));
+    println!("  expires:   {}", cycle.expires().format("%Y-%m-%d"));
+
+    if count == 0 {
+        return;
+    }
+    let mut table = Table::new();
+    table.set_header(vec!["Cycle", "Effective", "Expires", "Starts"]);
+    let mut c = cycle;
+    for _ in 0..count {
+        c = c.next();
+        let days = (c.effective - today).num_days();
+        let starts = if days > 0 {
+            format!("in {} days", days)
+        } else if c == current {
+            "in effect".to_string()
+        } else {
+            "expired".to_string()
+        };
+        table.add_row(vec![
+            c.ident(),
+            c.effective.format("%Y-%m-%d").to_string(),
+            c.expires().format("%Y-%m-%d").to_string(),
+            starts,
+        ]);
+    }
+    println!();
+    println!("{table}");
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ProcedureKind {
+    Sid,
+    Star,
+}
+
+impl std::fmt::Display for ProcedureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProcedureKind::Sid => "SID",
+            ProcedureKind::Star => "STAR",
+        })
+    }
+}
+
+/// One SID/STAR leg from a CIFP primary record.
+#[derive(Clone, Debug)]
+struct ProcedureLeg {
+    transition: String,
+    route_type: char,
+    fix: String,
+    /// ARINC path terminator, e.g. `TF`, `CF`, `VA`.
+    path: String,
+    /// Magnetic course in degrees, when the leg has one.
+    course: Option<f32>,
+    altitude: String,
+    speed: String,
+}
+
+#[derive(Clone, Debug)]
+struct Procedure {
+    ident: String,
+    kind: ProcedureKind,
+    cycle: String,
+    legs: Vec<ProcedureLeg>,
+}
+
+impl Procedure {
+    /// Transition names with their kind, in file order.
+    fn transitions(&self) -> Vec<(String, &'static str)> {
+        let mut seen = HashSet::new();
+        self.legs
+            .iter()
+            .filter(|l| seen.insert((l.transition.clone(), l.route_type)))
+            .map(|l| (l.transition.clone(), transition_kind(self.kind, l.route_type)))
+            .collect()
+    }
+}
+
+/// Runway / common / enroute, from the ARINC 424 route type of a SID or STAR record.
+fn transition_kind(kind: ProcedureKind, route_type: char) -> &'static str {
+    match (kind, route_type) {
+        (ProcedureKind::Sid, '0') => "engine out",
+        (ProcedureKind::Sid, '1' | '4' | 'F' | 'T') => "runway",
+        (ProcedureKind::Sid, '3' | '6' | 'S' | 'V') => "enroute",
+        (ProcedureKind::Star, '1' | '4' | '7' | 'F') => "enroute",
+        (ProcedureKind::Star, '3' | '6' | '9' | 'S') => "runway",
+        _ => "common",
+    }
+}
+
+/// Columns `from..=to` (1-based, as in the ARINC 424 spec) of a fixed-width record.
+fn arinc_field(line: &str, from: usize, to: usize) -> &str {
+    line.get(from - 1..to.min(line.len())).unwrap_or("").trim()
+}
+
+/// `+10000`, `-FL230`, `10000-12000` etc. from the altitude description and values.
+fn arinc_altitude(desc: &str, alt1: &str, alt2: &str) -> String {
+    if alt1.is_empty() {
+        return String::new();
+    }
+    match desc {
+        "+" => format!("+{alt1}"),
+        "-" => format!("-{alt1}"),
+        // between: altitude 2 is the floor, altitude 1 the ceiling
+        "B" => format!("{alt2}-{alt1}"),
+        "C" => format!("+{alt2}"),
+        _ => alt1.to_string(),
+    }
+}
+
+/// All SIDs and STARs for `airport` in a FAACIFP18 file, legs in file (sequence) order.
+fn parse_cifp_procedures(text: &str, airport: &str) -> Vec<Procedure> {
+    let ap = airport.trim().to_uppercase();
+    let icao = if ap.len() == 3 { format!("K{ap}") } else { ap.clone() };
+    let mut procs: Vec<Procedure> = Vec::new();
+    for line in text.lines() {
+        // SUSAP <airport> <icao region> D|E ...
+        if !line.starts_with('S') || arinc_field(line, 5, 5) != "P" {
+            continue;
+        }
+        let ident_ap = arinc_field(line, 7, 10);
+        if ident_ap != icao && ident_ap != ap {
+            continue;
+        }
+        let kind = match arinc_field(line, 13, 13) {
+            "D" => ProcedureKind::Sid,
+            "E" => ProcedureKind::Star,
+            _ => continue,
+        };
+        // continuation records (2+) carry no leg data we use
+        if !matches!(arinc_field(line, 39, 39), "" | "0" | "1") {
+            continue;
+        }
+        let ident = arinc_field(line, 14, 19).to_string();
+        let course = arinc_field(line, 71, 74)
+            .parse::<f32>()
+            .ok()
+            .map(|c| c / 10.0);
+        let speed = arinc_field(line, 100, 102).trim_start_matches('0').to_string();
+        let speed = match (arinc_field(line, 118, 118), speed.is_empty()) {
+            (_, true) => String::new(),
+            ("+", _) => format!("+{speed}"),
+            ("-", _) => format!("-{speed}"),
+            _ => speed,
+        };
+        let leg = ProcedureLeg {
+            transition: arinc_field(line, 21, 25).to_string(),
+            route_type: arinc_field(line, 20, 20).chars().next().unwrap_or(' '),
+            fix: arinc_field(line, 30, 34).to_string(),
+            path: arinc_field(line, 48, 49).to_string(),
+            course,
+            altitude: arinc_altitude(
+                arinc_field(line, 83, 83),
+                arinc_field(line, 85, 89),
+                arinc_field(line, 90, 94),
+            ),
+            speed,
+        };
+        match procs.iter_mut().find(|p| p.ident == ident && p.kind == kind) {
+            Some(p) => p.legs.push(leg),
+            None => procs.push(Procedure {
+                ident,
+                kind,
+                cycle: arinc_field(line, 129, 132).to_string(),
+                legs: vec![leg],
+            }),
+        }
+    }
+    procs
+}
+
+/// CIFP computer code for a typed procedure name: `"Cavlr Four (RNAV)"` -> `CAVLR4`.
+fn procedure_code(name: &str) -> String {
+    let digits = [
+        "ZERO", "ONE", "TWO", "THREE", "FOUR", "FIVE", "SIX", "SEVEN", "EIGHT", "NINE",
+    ];
+    name.to_uppercase()
+        .replace("(RNAV)", " ")
+        .split_whitespace()
+        .map(|w| match digits.iter().position(|d| *d == w) {
+            Some(n) => n.to_string(),
+            None => w.to_string(),
+        })
+        .collect::<String>()
+}
+
+fn cifp_path(arg: &CifpArg, cfg: &Config) -> PathBuf {
+    arg.cifp
+        .clone()
+        .or_else(|| std::env::var("ZDC_CIFP").ok().map(PathBuf::from))
+        .or_else(|| cfg.settings.cifp.clone())
+        .unwrap_or_else(|| cache_root().join("cifp").join("FAACIFP18"))
+}
+
+fn read_cifp(cifp: &PathBuf) -> Result<String, Box<dyn std::error::Error>> {
+    let data = fs::read(cifp).map_err(|e| {
+        format!(
+            "cannot read CIFP file {} ({}); download FAACIFP18 from https://www.faa.gov/air_traffic/flight_info/aeronav/digital_products/cifp/ and pass --cifp or set ZDC_CIFP",
+            cifp.display(),
+            e
+        )
+    })?;
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+fn handle_procedure(
+    cifp: &PathBuf,
+    airport: &str,
+    name: &[String],
+    transition: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = read_cifp(cifp)?;
+    let procs = parse_cifp_procedures(&text, airport);
+    if procs.is_empty() {
+        eprintln!("No SIDs or STARs for {} in {}", airport.to_uppercase(), cifp.display());
+        return Ok(());
+    }
+
+    if name.is_empty() {
+        let mut table = Table::new();
+        table.set_header(vec!["Procedure", "Type", "Transitions"]);
+        for p in procs.iter() {
+            let names: Vec<String> = p
+                .transitions()
+                .into_iter()
+                .filter(|(_, k)| *k == "enroute")
+                .map(|(t, _)| t)
+                .collect();
+            table.add_row(vec![p.ident.clone(), p.kind.to_string(), names.join(" ")]);
+        }
+        println!("{} procedures (CIFP {})", airport.to_uppercase(), procs[0].cycle);
+        println!("{table}");
+        return Ok(());
+    }
+
+    let code = procedure_code(&name.join(" "));
+    let letters = code.trim_end_matches(|c: char| c.is_ascii_digit());
+    let mut found: Vec<&Procedure> = procs.iter().filter(|p| p.ident == code).collect();
+    // an all-digit name leaves no prefix to match on
+    if found.is_empty() && !letters.is_empty() {
+        found = procs.iter().filter(|p| p.ident.starts_with(letters)).collect();
+    }
+    let proc = match found.as_slice() {
+        [p] => *p,
+        // a SID and a STAR can share a name; show both
+        [a, b] if a.ident == b.ident => {
+            for p in [a, b] {
+                print_procedure(p, transition);
+            }
+            return Ok(());
+        }
+        [] => {
+            let all: Vec<&str> = procs.iter().map(|p| p.ident.as_str()).collect();
+            eprintln!("No procedure {} at {}. Available: {}", code, airport.to_uppercase(), all.join(", "));
+            return Ok(());
+        }
+        many => {
+            let all: Vec<&str> = many.iter().map(|p| p.ident.as_str()).collect();
+            eprintln!("{} matches several procedures: {}", code, all.join(", "));
+            return Ok(());
+        }
+    };
+    print_procedure(proc, transition);
+    Ok(())
+}
+
+fn print_procedure(proc: &Procedure, only: Option<&str>) {
+    let transitions = proc.transitions();
+    println!("{} {} (CIFP {})", proc.ident, proc.kind, proc.cycle);
+    for kind in ["runway", "common", "enroute"] {
+        let names: Vec<&str> = transitions
+            .iter()
+            .filter(|(_, k)| *k == kind)
+            .map(|(t, _)| if t.is_empty() { "-" } else { t.as_str() })
+            .collect();
+        if !names.is_empty() {
+            println!("  {:<8} {}", format!("{kind}:"), names.join(" "));
+        }
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["Transition", "Fix", "Leg", "Course", "Altitude", "Speed"]);
+    let mut shown = 0;
+    for leg in proc.legs.iter() {
+        if let Some(t) = only
+            && !leg.transition.eq_ignore_ascii_case(t.trim())
+        {
+            continue;
+        }
+        let transition = if leg.transition.is_empty() {
+            format!("({})", transition_kind(proc.kind, leg.route_type))
+        } else {
+            leg.transition.clone()
+        };
+        table.add_row(vec![
+            transition,
+            leg.fix.clone(),
+            leg.path.clone(),
+            leg.course.map(|c| format!("{:03.0}", c)).unwrap_or_default(),
+            leg.altitude.clone(),
+            leg.speed.clone(),
+        ]);
+        shown += 1;
+    }
+    if shown == 0 {
+        eprintln!("No transition {} on {}", only.unwrap_or_default(), proc.ident);
+        return;
+    }
+    println!("{table}");
+    println!("(+ at or above, - at or below, A-B between)");
+}
+
+/// One runway end from a CIFP runway (PG) record.
+#[derive(Clone, Debug)]
+struct Runway {
+    /// `19C`, without the `RW` prefix.
+    ident: String,
+    length_ft: Option<u32>,
+    magnetic_bearing: f64,
+}
+
+impl Runway {
+    /// Runway number without the L/C/R suffix; parallel runways share it.
+    fn number(&self) -> &str {
+        self.ident.trim_end_matches(|c: char| c.is_ascii_alphabetic())
+    }
+}
+
+/// Runways and magnetic variation for an airport, from its CIFP PA and PG records.
+#[derive(Clone, Debug, Default)]
+struct AirportRunways {
+    /// Degrees, east positive.
+    magnetic_variation: f64,
+    runways: Vec<Runway>,
+}
+
+impl AirportRunways {
+    fn true_bearing(&self, rwy: &Runway) -> f64 {
+        (rwy.magnetic_bearing + self.magnetic_variation).rem_euclid(360.0)
+    }
+}
+
+fn parse_cifp_runways(text: &str, airport: &str) -> AirportRunways {
+    let ap = airport.trim().to_uppercase();
+    let icao = if ap.len() == 3 { format!("K{ap}") } else { ap.clone() };
+    let mut out = AirportRunways::default();
+    for line in text.lines() {
+        if !line.starts_with('S') || arinc_field(line, 5, 5) != "P" {
+            continue;
+        }
+        let ident_ap = arinc_field(line, 7, 10);
+        if (ident_ap != icao && ident_ap != ap) || !matches!(arinc_field(line, 22, 22), "" | "0" | "1") {
+            continue;
+        }
+        match arinc_field(line, 13, 13) {
+            // magnetic variation: E0110 / W0110, tenths of a degree
+            "A" => {
+                let var = arinc_field(line, 52, 56);
+                if let Some(tenths) = var.get(1..).and_then(|v| v.parse::<f64>().ok()) {
+                    let sign = if var.starts_with('W') { -1.0 } else { 1.0 };
+                    out.magnetic_variation = sign * tenths / 10.0;
+                }
+            }
+            "G" => {
+                let Some(ident) = arinc_field(line, 14, 18).strip_prefix("RW") else {
+                    continue;
+                };
+                // tenths of a degree magnetic; a trailing T marks a true bearing, which we skip
+                let Ok(bearing) = arinc_field(line, 28, 31).parse::<f64>() else {
+                    continue;
+                };
+                out.runways.push(Runway {
+                    ident: ident.to_string(),
+                    length_ft: arinc_field(line, 23, 27).parse().ok(),
+                    magnetic_bearing: bearing / 10.0,
+                });
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Headwind (negative = tailwind) and crosswind (positive = from the right) for a
+/// wind from `wind_from` degrees true on a runway heading `runway` degrees true.
+fn wind_components(wind_from: f64, speed: f64, runway: f64) -> (f64, f64) {
+    let angle = (wind_from - runway).to_radians();
+    (speed * angle.cos(), speed * angle.sin())
+}
+
+fn format_headwind(h: f64) -> String {
+    let kt = h.round() as i64;
+    match kt {
+        0 => "0".to_string(),
+        k if k > 0 => format!("{k} head"),
+        k => format!("{} tail", -k),
+    }
+}
+
+fn format_crosswind(x: f64) -> String {
+    let kt = x.round() as i64;
+    match kt {
+        0 => "0".to_string(),
+        k if k > 0 => format!("{k} R"),
+        k => format!("{} L", -k),
+    }
+}
+
+/// Current METAR for `airport`, decoded.
+async fn current_metar(
+    client: &reqwest::Client,
+    airport: &str,
+    offline: bool,
+) -> Result<Metar, Box<dyn std::error::Error>> {
+    let id = icao_station(airport);
+    let arr = into_vec(fetch_awc_cached(client, "metar", &id, None, offline).await?);
+    let raw = arr
+        .first()
+        .and_then(|e| get_str_field(e, "rawOb"))
+        .ok_or_else(|| format!("no METAR for {}", id))?;
+    Ok(parse_metar(&raw).map_err(|e| format!("cannot decode METAR for {} ({})", id, e))?)
+}
+
+async fn handle_winds(
+    client: &reqwest::Client,
+    cifp: &PathBuf,
+    airport: &str,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = read_cifp(cifp)?;
+    let apt = parse_cifp_runways(&text, airport);
+    if apt.runways.is_empty() {
+        eprintln!("No runways for {} in {}", airport.to_uppercase(), cifp.display());
+        return Ok(());
+    }
+    let metar = current_metar(client, airport, offline).await?;
+    println!("{}", metar.raw);
+    let Some(wind) = metar.conditions.wind.as_ref() else {
+        eprintln!("{} has no wind group", metar.station);
+        return Ok(());
+    };
+    let var = apt.magnetic_variation;
+    println!(
+        "Wind {} (true), magnetic variation {:.0}°{}",
+        wind,
+        var.abs(),
+        if var < 0.0 { "W" } else { "E" }
+    );
+    let Some(from) = wind.direction.filter(|_| !wind.is_calm()) else {
+        println!("Calm or variable wind: no favored runway; use the calm-wind runway.");
+        return Ok(());
+    };
+    let from = from as f64;
+    let speed = wind.knots(wind.speed);
+    let gust = wind.gust.map(|g| wind.knots(g));
+
+    let mut table = Table::new();
+    table.set_header(vec!["Runway", "Hdg (M)", "Length", "Headwind", "Crosswind", "Gust"]);
+    let mut best: Option<(&Runway, f64, f64)> = None;
+    for rwy in apt.runways.iter() {
+        let heading = apt.true_bearing(rwy);
+        let (head, cross) = wind_components(from, speed, heading);
+        let gust_text = gust
+            .map(|g| {
+                let (h, x) = wind_components(from, g, heading);
+                format!("{}, {}", format_headwind(h), format_crosswind(x))
+            })
+            .unwrap_or_default();
+        table.add_row(vec![
+            rwy.ident.clone(),
+            format!("{:03.0}", rwy.magnetic_bearing),
+            rwy.length_ft.map(|l| format!("{l} ft")).unwrap_or_default(),
+            format_headwind(head),
+            format_crosswind(cross),
+            gust_text,
+        ]);
+        let better = match best {
+            None => true,
+            Some((b, h, _)) => {
+                head > h + 0.5 || ((head - h).abs() <= 0.5 && rwy.length_ft > b.length_ft)
+            }
+        };
+        if better {
+            best = Some((rwy, head, cross));
+        }
+    }
+    println!("{table}");
+    if let Some((rwy, head, cross)) = best {
+        // parallel runways share the favored direction
+        let flow: Vec<&str> = apt
+            .runways
+            .iter()
+            .filter(|r| r.number() == rwy.number())
+            .map(|r| r.ident.as_str())
+            .collect();
+        println!(
+            "Favored: runway{} {} ({} kt headwind, {} kt crosswind)",
+            if flow.len() > 1 { "s" } else { "" },
+            flow.join("/"),
+            head.round() as i64,
+            cross.abs().round() as i64
+        );
+    }
+    Ok(())
+}
 
-            let period = if !from.is_empty() || !to.is_empty() {
-                format!("{} - {}", from, to)
-            } else {
-                "".to_string()
-            };
+/// The `[airports.XXX]` tables of a `flows.toml`; a user airport replaces the shipped one whole.
+#[derive(Default, Deserialize)]
+struct FlowFile {
+    #[serde(default)]
+    airports: HashMap<String, FlowRules>,
+}
 
-            table.add_row(vec![
-                period.as_str(),
-                wind.as_str(),
-                vis.as_str(),
-                wx.as_str(),
-                alt.as_str(),
-                clouds.as_str(),
-            ]);
-        }
+/// An airport's runway configurations and the limits for choosing between them.
+#[derive(Clone, Debug, Deserialize)]
+struct FlowRules {
+    /// Flow kept whenever it is within limits (noise or calm-wind preference).
+    preferred: Option<String>,
+    #[serde(default = "default_max_tailwind")]
+    max_tailwind: f64,
+    #[serde(default = "default_max_crosswind")]
+    max_crosswind: f64,
+    #[serde(default)]
+    flows: Vec<RunwayFlow>,
+}
+
+fn default_max_tailwind() -> f64 {
+    5.0
+}
+
+fn default_max_crosswind() -> f64 {
+    20.0
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RunwayFlow {
+    name: String,
+    arrivals: Vec<String>,
+    departures: Vec<String>,
+    max_tailwind: Option<f64>,
+    max_crosswind: Option<f64>,
+    note: Option<String>,
+}
+
+fn flows_path() -> PathBuf {
+    if let Ok(p) = std::env::var("ZDC_FLOWS") {
+        return PathBuf::from(p);
     }
+    config_path()
+        .parent()
+        .map(|d| d.join("flows.toml"))
+        .unwrap_or_else(|| PathBuf::from("flows.toml"))
+}
 
-    table
+/// Shipped runway configurations with the user's airports laid over them.
+fn flow_rules(airport: &str) -> Option<FlowRules> {
+    let mut table: HashMap<String, FlowRules> = HashMap::new();
+    let shipped: FlowFile = toml::from_str(include_str!("flows.toml")).expect("built-in flows.toml is valid");
+    let path = flows_path();
+    let user = match fs::read_to_string(&path) {
+        Ok(s) => match toml::from_str::<FlowFile>(&s) {
+            Ok(f) => f,
+            Err(e) => {
+                eprintln!("ignoring {:?}: {}", path, e);
+                FlowFile::default()
+            }
+        },
+        Err(_) => FlowFile::default(),
+    };
+    for (ap, rules) in shipped.airports.into_iter().chain(user.airports) {
+        table.insert(norm_airport_for_routes(&ap), rules);
+    }
+    table.remove(&norm_airport_for_routes(airport))
 }
 
-async fn handle_metar(
-    client: &reqwest::Client,
-    station: &str,
-    raw: bool,
-    json: bool,
-    _verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut st = station.trim().to_uppercase();
-    let mut data = fetch_awc(client, "metar", &st, "json").await?;
-    let mut arr = into_vec(data);
-    if arr.is_empty() && st.len() == 3 && !st.starts_with('K') {
-        st = format!("K{}", st);
-        data = fetch_awc(client, "metar", &st, "json").await?;
-        arr = into_vec(data);
+/// `01C` and `1C` name the same runway.
+fn runway_key(ident: &str) -> String {
+    let id = ident.trim().to_uppercase();
+    let id = id.strip_prefix("RW").unwrap_or(&id);
+    id.trim_start_matches('0').to_string()
+}
+
+/// Worst-case wind on a flow's runways.
+struct FlowCheck {
+    /// Tailwind (gusts included) on the worst runway; 0 when every runway has headwind.
+    tailwind: f64,
+    crosswind: f64,
+    /// Steady headwind on the least favorable arrival runway.
+    arrival_headwind: f64,
+    /// Why the flow is over its limits, if it is.
+    exceeds: Option<String>,
+    /// False when none of the flow's runways are in the CIFP, so nothing was checked.
+    checked: bool,
+}
+
+fn check_flow(
+    flow: &RunwayFlow,
+    rules: &FlowRules,
+    apt: &AirportRunways,
+    wind_from: f64,
+    speed: f64,
+    gust: f64,
+) -> FlowCheck {
+    let find = |id: &String| apt.runways.iter().find(|r| runway_key(&r.ident) == runway_key(id));
+    if flow.arrivals.iter().chain(flow.departures.iter()).all(|id| find(id).is_none()) {
+        return FlowCheck {
+            tailwind: 0.0,
+            crosswind: 0.0,
+            arrival_headwind: f64::NEG_INFINITY,
+            exceeds: Some("unchecked: no runways in CIFP".to_string()),
+            checked: false,
+        };
     }
-    if arr.is_empty() {
-        eprintln!("No METAR data found for {}", st);
-        return Ok(());
+    let mut tailwind: f64 = 0.0;
+    let mut crosswind: f64 = 0.0;
+    for rwy in flow.arrivals.iter().chain(flow.departures.iter()).filter_map(find) {
+        let (head, cross) = wind_components(wind_from, gust, apt.true_bearing(rwy));
+        tailwind = tailwind.max(-head);
+        crosswind = crosswind.max(cross.abs());
     }
-    if json {
-        println!("{}", serde_json::to_string_pretty(&arr)?);
-        return Ok(());
+    let arrival_headwind = flow
+        .arrivals
+        .iter()
+        .filter_map(find)
+        .map(|r| wind_components(wind_from, speed, apt.true_bearing(r)).0)
+        .reduce(f64::min)
+        .unwrap_or(f64::NEG_INFINITY);
+    let max_tail = flow.max_tailwind.unwrap_or(rules.max_tailwind);
+    let max_cross = flow.max_crosswind.unwrap_or(rules.max_crosswind);
+    let exceeds = if tailwind.round() > max_tail {
+        Some(format!("tailwind {:.0} kt > {:.0} kt", tailwind, max_tail))
+    } else if crosswind.round() > max_cross {
+        Some(format!("crosswind {:.0} kt > {:.0} kt", crosswind, max_cross))
+    } else {
+        None
+    };
+    FlowCheck {
+        tailwind,
+        crosswind,
+        arrival_headwind,
+        exceeds,
+        checked: true,
     }
-    for entry in arr {
-        let rawtxt = get_str_field(&entry, "rawOb")
-            .or_else(|| get_str_field(&entry, "raw_text"))
-            .unwrap_or_default();
-        if !rawtxt.is_empty() {
-            println!("{}", rawtxt);
-            println!();
-        } else if raw {
-            println!("{}", serde_json::to_string_pretty(&entry)?);
-            println!();
+}
+
+/// Every flow checked against the report's wind; `None` when it is calm or variable.
+fn flow_checks(rules: &FlowRules, apt: &AirportRunways, metar: &Metar) -> Option<Vec<FlowCheck>> {
+    let w = metar.conditions.wind.as_ref().filter(|w| !w.is_calm())?;
+    let d = w.direction? as f64;
+    let speed = w.knots(w.speed);
+    let gust = w.gust.map(|g| w.knots(g)).unwrap_or(speed);
+    Some(
+        rules
+            .flows
+            .iter()
+            .map(|f| check_flow(f, rules, apt, d, speed, gust))
+            .collect(),
+    )
+}
+
+/// Index of the recommended flow and the rule that chose it.
+fn recommend_flow(rules: &FlowRules, checks: Option<&[FlowCheck]>) -> (usize, String) {
+    let preferred = rules
+        .preferred
+        .as_ref()
+        .and_then(|p| rules.flows.iter().position(|f| f.name.eq_ignore_ascii_case(p)));
+    let Some(checks) = checks else {
+        return match preferred {
+            Some(i) => (i, format!("calm or variable wind: preferred {} flow", rules.flows[i].name)),
+            None => (0, format!("calm or variable wind: first listed flow ({})", rules.flows[0].name)),
+        };
+    };
+    if let Some(i) = preferred
+        && checks[i].exceeds.is_none()
+    {
+        return (
+            i,
+            format!(
+                "preferred {} flow is within limits (tailwind {:.0} kt, crosswind {:.0} kt)",
+                rules.flows[i].name, checks[i].tailwind, checks[i].crosswind
+            ),
+        );
+    }
+    let lead = match preferred {
+        Some(i) if !checks[i].checked => {
+            format!("preferred {} flow could not be checked (no runways in CIFP); ", rules.flows[i].name)
         }
-        let table = build_metar_table(&entry);
-        println!("{table}");
+        Some(i) => format!(
+            "preferred {} flow exceeds limits ({}); ",
+            rules.flows[i].name,
+            checks[i].exceeds.as_deref().unwrap_or_default()
+        ),
+        None => String::new(),
+    };
+    let within = checks.iter().enumerate().filter(|(_, c)| c.exceeds.is_none());
+    if let Some((i, c)) = within.max_by(|a, b| a.1.arrival_headwind.total_cmp(&b.1.arrival_headwind)) {
+        return (
+            i,
+            format!(
+                "{}{} flow has the most arrival headwind ({:.0} kt) within limits",
+                lead, rules.flows[i].name, c.arrival_headwind
+            ),
+        );
     }
-    Ok(())
+    let Some((i, c)) = checks
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.checked)
+        .min_by(|a, b| a.1.tailwind.total_cmp(&b.1.tailwind).then(a.1.crosswind.total_cmp(&b.1.crosswind)))
+    else {
+        let i = preferred.unwrap_or(0);
+        return (i, format!("no flow could be checked against the CIFP; showing {} flow", rules.flows[i].name));
+    };
+    (
+        i,
+        format!(
+            "{}no flow is within limits; {} flow has the least tailwind ({:.0} kt)",
+            lead, rules.flows[i].name, c.tailwind
+        ),
+    )
 }
 
-async fn handle_taf(
+async fn handle_config(
     client: &reqwest::Client,
-    station: &str,
-    raw: bool,
-    json: bool,
-    _verbose: bool,
+    cifp: &PathBuf,
+    airport: &str,
+    offline: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut st = station.trim().to_uppercase();
-    let mut data = fetch_awc(client, "taf", &st, "json").await?;
-    let mut arr = into_vec(data);
-    if arr.is_empty() && st.len() == 3 && !st.starts_with('K') {
-        st = format!("K{}", st);
-        data = fetch_awc(client, "taf", &st, "json").await?;
-        arr = into_vec(data);
-    }
-    if arr.is_empty() {
-        eprintln!("No TAF data found for {}", st);
+    let Some(rules) = flow_rules(airport).filter(|r| !r.flows.is_empty()) else {
+        eprintln!(
+            "No runway configurations for {}. Add [[airports.{}.flows]] to {:?}.",
+            airport.to_uppercase(),
+            norm_airport_for_routes(airport),
+            flows_path()
+        );
         return Ok(());
+    };
+    let text = read_cifp(cifp)?;
+    let apt = parse_cifp_runways(&text, airport);
+    if apt.runways.is_empty() {
+        return Err(format!("No runways for {} in {}", airport.to_uppercase(), cifp.display()).into());
     }
-    if json {
-        println!("{}", serde_json::to_string_pretty(&arr)?);
-        return Ok(());
+    let unknown: Vec<&str> = rules
+        .flows
+        .iter()
+        .flat_map(|f| f.arrivals.iter().chain(f.departures.iter()))
+        .filter(|id| !apt.runways.iter().any(|r| runway_key(&r.ident) == runway_key(id)))
+        .map(|id| id.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        eprintln!("Runways not in {}: {} (ignored)", cifp.display(), unknown.join(", "));
     }
-    for entry in arr {
-        let rawtxt = get_str_field(&entry, "rawTAF").unwrap_or_default();
-        if !rawtxt.is_empty() {
-            println!("{}", rawtxt);
-            println!();
-        } else if raw {
-            println!("{}", serde_json::to_string_pretty(&entry)?);
-            println!();
+    let metar = current_metar(client, airport, offline).await?;
+    println!("{}", metar.raw);
+
+    let checks = flow_checks(&rules, &apt, &metar);
+    let (chosen, reason) = recommend_flow(&rules, checks.as_deref());
+
+    let mut table = Table::new();
+    table.set_header(vec!["Flow", "Arrivals", "Departures", "Tailwind", "Crosswind", "Status"]);
+    for (i, flow) in rules.flows.iter().enumerate() {
+        let check = checks.as_ref().map(|c| &c[i]);
+        let mut status = match check.and_then(|c| c.exceeds.clone()) {
+            Some(e) => e,
+            None => "ok".to_string(),
+        };
+        if rules.preferred.as_deref().is_some_and(|p| flow.name.eq_ignore_ascii_case(p)) {
+            status.push_str(" (preferred)");
         }
-        let station_name = get_str_field(&entry, "icaoId").unwrap_or_else(|| st.clone());
-        let issue = get_str_field(&entry, "issueTime").unwrap_or_default();
-        let valid_from = entry
-            .get("validTimeFrom")
-            .and_then(|n| n.as_i64())
-            .map(format_unix)
-            .unwrap_or_default();
-        let valid_to = entry
-            .get("validTimeTo")
-            .and_then(|n| n.as_i64())
-            .map(format_unix)
-            .unwrap_or_default();
-        let header = format!(
-            "{}  issued: {}  valid: {} - {}",
-            station_name, issue, valid_from, valid_to
-        );
-        println!("{header}");
-        let taf_table = build_taf_table(&entry);
-        println!("{taf_table}");
+        table.add_row(vec![
+            if i == chosen { format!("{} *", flow.name) } else { flow.name.clone() },
+            flow.arrivals.join(" "),
+            flow.departures.join(" "),
+            check.filter(|c| c.checked).map(|c| format!("{:.0} kt", c.tailwind)).unwrap_or_default(),
+            check.filter(|c| c.checked).map(|c| format!("{:.0} kt", c.crosswind)).unwrap_or_default(),
+            status,
+        ]);
+    }
+    println!("{table}");
+    let flow = &rules.flows[chosen];
+    println!(
+        "Recommended: {} flow, arrivals {}, departures {}",
+        flow.name,
+        flow.arrivals.join(", "),
+        flow.departures.join(", ")
+    );
+    println!("Rule: {}", reason);
+    if let Some(note) = &flow.note {
+        println!("Note: {}", note);
     }
     Ok(())
 }
 
-#[allow(dead_code)]
-fn flatten_text(v: &Value) -> String {
-    match v {
-        Value::String(s) => s.to_lowercase(),
-        Value::Number(n) => n.to_string(),
-        Value::Bool(b) => b.to_string(),
-        Value::Array(a) => a.iter().map(flatten_text).collect::<Vec<_>>().join(" "),
-        Value::Object(o) => o.values().map(flatten_text).collect::<Vec<_>>().join(" "),
-        Value::Null => "".to_string(),
+const PHONETIC: [&str; 26] = [
+    "ALFA", "BRAVO", "CHARLIE", "DELTA", "ECHO", "FOXTROT", "GOLF", "HOTEL", "INDIA", "JULIETT",
+    "KILO", "LIMA", "MIKE", "NOVEMBER", "OSCAR", "PAPA", "QUEBEC", "ROMEO", "SIERRA", "TANGO",
+    "UNIFORM", "VICTOR", "WHISKEY", "XRAY", "YANKEE", "ZULU",
+];
+
+fn phonetic(letter: char) -> &'static str {
+    let i = (letter.to_ascii_uppercase() as usize).wrapping_sub('A' as usize);
+    PHONETIC.get(i).copied().unwrap_or(PHONETIC[0])
+}
+
+fn parse_atis_letter(s: &str) -> Result<char, String> {
+    let t = s.trim().to_uppercase();
+    // ICAO spells ALFA and JULIETT; accept the everyday spellings too
+    let spelled = PHONETIC.contains(&t.as_str()) || t == "ALPHA" || t == "JULIET";
+    match t.chars().next() {
+        Some(c) if c.is_ascii_uppercase() && (t.len() == 1 || spelled) => Ok(c),
+        _ => Err(format!("'{}' is not an ATIS letter (A-Z)", s)),
     }
 }
 
-#[allow(dead_code)]
-fn find_first_pdf_url(v: &Value, base: &str) -> Option<String> {
-    // collect exact-URL candidates first, fallback to bare filenames
-    let mut full_urls: Vec<String> = Vec::new();
-    let mut file_names: Vec<String> = Vec::new();
+/// Current ATIS letter per airport and the METAR it was issued for.
+#[derive(Default, Deserialize, Serialize)]
+struct AtisState {
+    #[serde(default)]
+    airports: BTreeMap<String, AtisEntry>,
+}
 
-    fn rec(val: &Value, full_urls: &mut Vec<String>, file_names: &mut Vec<String>) {
-        match val {
-            Value::String(s) => {
-                let s = s.trim();
-                if !s.to_lowercase().contains(".pdf") {
-                    return;
-                }
-                if s.starts_with("http://")
-                    || s.starts_with("https://")
-                    || s.starts_with("file://")
-                    || s.starts_with("//")
-                {
-                    full_urls.push(s.to_string());
-                } else {
-                    file_names.push(s.to_string());
-                }
-            }
-            Value::Array(arr) => {
-                for it in arr {
-                    rec(it, full_urls, file_names);
-                }
-            }
-            Value::Object(map) => {
-                for (_k, v) in map {
-                    rec(v, full_urls, file_names);
-                }
+#[derive(Clone, Deserialize, Serialize)]
+struct AtisEntry {
+    letter: char,
+    metar: String,
+    issued_at: i64,
+}
+
+fn atis_state_path() -> PathBuf {
+    if let Ok(p) = std::env::var("ZDC_ATIS_STATE") {
+        return PathBuf::from(p);
+    }
+    config_path()
+        .parent()
+        .map(|d| d.join("atis.json"))
+        .unwrap_or_else(|| PathBuf::from("atis.json"))
+}
+
+impl AtisState {
+    fn load() -> AtisState {
+        let mut state: AtisState = fs::read_to_string(atis_state_path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        // the file is hand-editable; anything that is not a letter starts over at A
+        for e in state.airports.values_mut() {
+            e.letter = e.letter.to_ascii_uppercase();
+            if !e.letter.is_ascii_uppercase() {
+                e.letter = 'A';
             }
-            _ => {}
         }
+        state
     }
 
-    rec(v, &mut full_urls, &mut file_names);
-
-    // prefer absolute/complete URLs
-    if let Some(u) = full_urls.into_iter().next() {
-        if u.starts_with("//") {
-            return Some(format!("https:{}", u));
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = atis_state_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
         }
-        return Some(u);
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
     }
 
-    // build an absolute URL from a bare filename or relative path
-    if let Some(name) = file_names.into_iter().next() {
-        let base = base.trim_end_matches('/');
-        // derive domain base (strip /v2 if present) so we can join leading '/...' paths sensibly
-        let domain_base = if let Some(pos) = base.find("/v2") {
-            &base[..pos]
-        } else {
-            base
+    /// Letter for `metar`: the forced one, the current one while the METAR is unchanged,
+    /// otherwise the next letter (A after Z, and A for a new airport).
+    fn letter_for(&mut self, airport: &str, metar: &str, forced: Option<char>) -> char {
+        let current = self.airports.get(airport);
+        let letter = match (forced, current) {
+            (Some(l), _) => l,
+            (None, Some(e)) if e.metar == metar => e.letter,
+            (None, Some(e)) => (b'A' + (e.letter as u8 - b'A' + 1) % 26) as char,
+            (None, None) => 'A',
         };
-
-        if name.starts_with('/') {
-            // e.g. "/v2/charts/..." or "/files/..."
-            return Some(format!("{}{}", domain_base.trim_end_matches('/'), name));
+        if current.is_none_or(|e| e.letter != letter || e.metar != metar) {
+            self.airports.insert(
+                airport.to_string(),
+                AtisEntry {
+                    letter,
+                    metar: metar.to_string(),
+                    issued_at: Utc::now().timestamp(),
+                },
+            );
         }
-
-        // if we only have a filename, try sensible locations under the provided base
-        // prefer base/charts/<name> so we cover api-v2 hosting patterns
-        return Some(format!("{}/charts/{}", base, name));
+        letter
     }
-
-    None
 }
 
-async fn handle_chart(
-    client: &reqwest::Client,
-    airport: &str,
-    query: &[String],
-    link_only: bool,
-    _airac: Option<i32>,
-    auto_open: bool,
-    verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let default_base = "https://api-v2.aviationapi.com/v2";
-    let base = std::env::var("ZDC_CHARTS_BASE").unwrap_or_else(|_| default_base.into());
-
-    if verbose {
-        eprintln!("charts base: {}", base);
-        eprintln!("airport arg: {}", airport);
-        eprintln!("query tokens: {:?}", query);
+/// `1 1/2`, `3/4`, `10` — visibility the way it is read on frequency.
+fn spoken_visibility(v: &Visibility) -> String {
+    let whole = v.statute_miles.trunc() as u32;
+    let frac = v.statute_miles.fract();
+    let eighths = (frac * 8.0).round() as u32;
+    let frac_text = match eighths {
+        0 | 8 => String::new(),
+        1 => "1/8".to_string(),
+        2 => "1/4".to_string(),
+        3 => "3/8".to_string(),
+        4 => "1/2".to_string(),
+        5 => "5/8".to_string(),
+        6 => "3/4".to_string(),
+        _ => "7/8".to_string(),
+    };
+    let whole = if eighths == 8 { whole + 1 } else { whole };
+    let num = match (whole, frac_text.is_empty()) {
+        (0, false) => frac_text,
+        (w, true) => w.to_string(),
+        (w, false) => format!("{w} {frac_text}"),
+    };
+    if v.greater_than {
+        format!("MORE THAN {num}")
+    } else if v.less_than {
+        format!("LESS THAN {num}")
+    } else {
+        num
     }
+}
 
-    let mut charts = fetch_charts_from_api(client, &base, airport).await?;
-    if charts.is_empty() && airport.len() == 3 && !airport.starts_with('K') {
-        let k_air = format!("K{}", airport.to_uppercase());
-        if verbose {
-            eprintln!("retry GET {}/charts?apt={}", base, k_air);
+fn spoken_sky(sky: &[SkyLayer]) -> String {
+    let mut parts = Vec::new();
+    let mut ceiling_said = false;
+    for l in sky {
+        let base = l.base_ft.map(|b| b.to_string()).unwrap_or_default();
+        let mut text = match l.cover.as_str() {
+            "FEW" => format!("FEW CLOUDS AT {base}"),
+            "SCT" => format!("SCATTERED CLOUDS AT {base}"),
+            "VV" => format!("INDEFINITE CEILING {base}"),
+            "BKN" | "OVC" => {
+                let cover = if l.cover == "BKN" { "BROKEN" } else { "OVERCAST" };
+                if ceiling_said {
+                    format!("{base} {cover}")
+                } else {
+                    format!("CEILING {base} {cover}")
+                }
+            }
+            _ => "SKY CLEAR".to_string(),
+        };
+        ceiling_said |= l.is_ceiling();
+        match l.cloud.as_deref() {
+            Some("CB") => text.push_str(" CUMULONIMBUS"),
+            Some("TCU") => text.push_str(" TOWERING CUMULUS"),
+            _ => {}
         }
-        charts = fetch_charts_from_api(client, &base, &k_air).await?;
+        parts.push(text);
     }
+    parts.join(", ")
+}
 
-    if charts.is_empty() {
-        eprintln!("No charts found for {}", airport);
-        return Ok(());
-    }
+fn spoken_temp(t: f64) -> String {
+    let t = t.round() as i64;
+    if t < 0 { format!("MINUS {}", -t) } else { t.to_string() }
+}
 
-    let q_str = query.join(" ");
-    let cq = ChartQuery::new(airport, &q_str);
-    let (maybe_chart, _matches) = find_chart_by_name(&charts, &cq, 0.15);
+fn spoken_runways(kind: &str, runways: &[String]) -> String {
+    let s = if runways.len() > 1 { "S" } else { "" };
+    format!("{kind} RUNWAY{s} {}", runways.join(", "))
+}
 
-    if maybe_chart.is_none() {
-        let mut table = comfy_table::Table::new();
-        table.set_header(vec!["Idx", "Title / Name", "Likely PDF"]);
-        for (i, ch) in charts.iter().take(12).enumerate() {
-            let pdf = absolute_pdf_url(&base, &ch.pdf_path);
-            table.add_row(vec![
-                i.to_string().as_str(),
-                ch.chart_name.as_str(),
-                pdf.as_str(),
-            ]);
+/// ATIS broadcast text for `metar`, wind converted to magnetic with `variation` (east positive).
+fn atis_text(
+    name: &str,
+    letter: char,
+    metar: &Metar,
+    variation: f64,
+    atis: &AtisConfig,
+    arrivals: &[String],
+    departures: &[String],
+) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    parts.push(format!("{} INFORMATION {}", name.to_uppercase(), phonetic(letter)));
+    let mut time = metar
+        .time
+        .map(|t| format!("{:02}{:02}Z", t.hour, t.minute))
+        .unwrap_or_default();
+    if metar.kind == "SPECI" {
+        time.push_str(" SPECIAL");
+    }
+    parts.push(time);
+    let c = &metar.conditions;
+    if let Some(w) = &c.wind {
+        let magnetic = |d: u16| {
+            let m = ((d as f64 - variation) / 10.0).round() as i64 * 10;
+            format!("{:03}", if m <= 0 { m + 360 } else { m.min(360) })
+        };
+        let speed = w.knots(w.speed).round() as i64;
+        let mut wind = match w.direction {
+            _ if w.is_calm() => "WIND CALM".to_string(),
+            Some(d) => format!("WIND {} AT {}", magnetic(d), speed),
+            None => format!("WIND VARIABLE AT {}", speed),
+        };
+        if let Some(g) = w.gust {
+            wind.push_str(&format!(" GUST {}", w.knots(g).round() as i64));
+        }
+        if let (Some(a), Some(b)) = (w.variable_from, w.variable_to) {
+            wind.push_str(&format!(", WIND VARIABLE BETWEEN {} AND {}", magnetic(a), magnetic(b)));
+        }
+        parts.push(wind);
+    }
+    if c.cavok {
+        parts.push("CAVOK".to_string());
+    } else if let Some(v) = &c.visibility {
+        parts.push(format!("VISIBILITY {}", spoken_visibility(v)));
+    }
+    for r in &metar.rvr {
+        let q = match r.qualifier {
+            Some('M') => "LESS THAN ",
+            Some('P') => "MORE THAN ",
+            _ => "",
+        };
+        let mut rvr = format!("RUNWAY {} RVR {q}{}", r.runway, r.feet);
+        if let Some(to) = r.variable_to {
+            rvr.push_str(&format!(" VARIABLE TO {to}"));
+        }
+        parts.push(rvr);
+    }
+    if !c.weather.is_empty() {
+        parts.push(c.weather_text().to_uppercase());
+    }
+    if !c.sky.is_empty() {
+        parts.push(spoken_sky(&c.sky));
+    }
+    if let Some(t) = metar.temperature_c {
+        let mut td = format!("TEMPERATURE {}", spoken_temp(t));
+        if let Some(d) = metar.dewpoint_c {
+            td.push_str(&format!(", DEWPOINT {}", spoken_temp(d)));
         }
-        println!("Multiple possible charts (no strong match).");
-        println!("{table}");
-        println!("Refine your query or pass a more specific string.");
-        return Ok(());
+        parts.push(td);
+    }
+    if let Some(a) = metar.altimeter_inhg {
+        parts.push(format!("ALTIMETER {:04.0}", a * 100.0));
+    }
+    if let Some(app) = &atis.approaches {
+        parts.push(app.to_uppercase());
+    }
+    if !arrivals.is_empty() {
+        parts.push(spoken_runways("LANDING", arrivals));
+    }
+    if !departures.is_empty() {
+        parts.push(spoken_runways("DEPARTING", departures));
     }
+    if !atis.notams.is_empty() {
+        parts.push("NOTAMS".to_string());
+        parts.extend(atis.notams.iter().map(|n| n.trim().trim_end_matches('.').to_uppercase()));
+    }
+    parts.extend(atis.remarks.iter().map(|r| r.trim().trim_end_matches('.').to_uppercase()));
+    parts.push(format!("ADVISE ON INITIAL CONTACT YOU HAVE INFORMATION {}", phonetic(letter)));
+    parts
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| format!("{p}."))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    let chart = maybe_chart.unwrap();
-    let pages = find_all_chart_pages(&charts, &chart);
-    let pdf_urls: Vec<String> =
-        pages.into_iter().map(|p| absolute_pdf_url(&base, &p.pdf_path)).collect();
+async fn handle_atis(
+    client: &reqwest::Client,
+    cfg: &Config,
+    cifp: &PathBuf,
+    airport: &str,
+    letter: Option<char>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let key = norm_airport_for_routes(airport);
+    let atis = cfg
+        .atis
+        .iter()
+        .find(|(k, _)| norm_airport_for_routes(k) == key)
+        .map(|(_, v)| v.clone())
+        .unwrap_or_default();
+    let metar = current_metar(client, airport, offline).await?;
 
-    if link_only || !auto_open {
-        for u in pdf_urls.iter() {
-            println!("{}", u);
+    // runway headings and magnetic variation; the ATIS still goes out without them
+    let apt = match read_cifp(cifp) {
+        Ok(text) => parse_cifp_runways(&text, airport),
+        Err(e) => {
+            eprintln!("{e}");
+            AirportRunways::default()
         }
-        return Ok(());
+    };
+    if apt.runways.is_empty() {
+        eprintln!("No CIFP data for {}: wind is given in degrees true", key);
+    }
+    let (mut arrivals, mut departures) = (atis.arrivals.clone(), atis.departures.clone());
+    if arrivals.is_empty()
+        && departures.is_empty()
+        && !apt.runways.is_empty()
+        && let Some(rules) = flow_rules(airport).filter(|r| !r.flows.is_empty())
+    {
+        let checks = flow_checks(&rules, &apt, &metar);
+        let (chosen, reason) = recommend_flow(&rules, checks.as_deref());
+        eprintln!("Runways: {} flow ({})", rules.flows[chosen].name, reason);
+        arrivals = rules.flows[chosen].arrivals.clone();
+        departures = rules.flows[chosen].departures.clone();
     }
 
-    if let Some(url) = pdf_urls.first() {
-        if verbose {
-            eprintln!("Opening {}", url);
-        }
-        match open::that(url) {
-            Ok(_) => return Ok(()),
-            Err(e) => {
-                eprintln!("failed to open: {e}");
-                println!("{}", url);
-                for u in pdf_urls.iter().skip(1) {
-                    println!("{}", u);
-                }
-                return Ok(());
-            }
-        }
+    let mut state = AtisState::load();
+    let previous = state.airports.get(&key).map(|e| e.letter);
+    let letter = state.letter_for(&key, &metar.raw, letter);
+    if previous.is_some_and(|p| p != letter) {
+        eprintln!("Information {} -> {}", phonetic(previous.unwrap_or(letter)), phonetic(letter));
     }
+    state.save()?;
 
+    let name = atis
+        .name
+        .clone()
+        .or_else(|| alias_table().airport_name(&key).map(|n| n.to_string()))
+        .unwrap_or_else(|| metar.station.clone());
+    println!(
+        "{}",
+        atis_text(&name, letter, &metar, apt.magnetic_variation, &atis, &arrivals, &departures)
+    );
     Ok(())
 }
 
@@ -1089,105 +6446,1156 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let client = reqwest::Client::new();
+    let offline = args.offline || cfg.settings.offline;
+    let provider = chart_provider(&cfg.charts);
 
     if let Some(cmd) = args.command {
         match cmd {
             Commands::Route { origin, destination, raw } => {
-                let origin = norm_airport_for_routes(&origin);
-                let destination = norm_airport_for_routes(&destination);
-                let url = format!("https://api.aviationapi.com/v1/preferred-routes/search?origin={}&dest={}",
-                                  origin, destination);
-                if args.verbose { eprintln!("GET {}", url); }
-                let resp = client.get(&url).send().await?;
-                let status = resp.status();
-                let body = resp.text().await?;
-                if !status.is_success() {
-                    eprintln!("api error {}: {}", status, body);
-                    std::process::exit(1);
-                }
-                let json: Value = serde_json::from_str(&body)?;
-                let rows = match json {
-                    Value::Array(a) => a,
-                    other => vec![other],
-                };
-                if rows.is_empty() {
-                    println!("No preferred routes found for {} -> {}", origin, destination);
-                    return Ok(());
-                }
-                if raw {
-                    println!("{}", serde_json::to_string_pretty(&rows)?);
-                    return Ok(());
-                }
-                let mut keys = BTreeSet::new();
-                for v in &rows {
-                    if let Value::Object(map) = v {
-                        for k in map.keys() {
-                            keys.insert(k.clone());
-                        }
-                    } else {
-                        keys.insert("value".to_string());
-                    }
-                }
-                let headers: Vec<String> = keys.into_iter().collect();
-                let mut table = Table::new();
-                table.set_header(headers.iter().map(|s| s.as_str()).collect::<Vec<_>>());
-                for v in rows {
-                    let mut cells = Vec::with_capacity(headers.len());
-                    for k in &headers {
-                        let s = if let Value::Object(map) = &v {
-                            let val = map.get(k).unwrap_or(&Value::Null);
-                            match val {
-                                Value::String(s) => s.clone(),
-                                Value::Number(n) => n.to_string(),
-                                Value::Bool(b) => b.to_string(),
-                                Value::Array(a) => a.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "),
-                                Value::Object(_) => serde_json::to_string(val).unwrap_or_default(),
-                                Value::Null => "".to_string(),
-                            }
-                        } else {
-                            v.to_string()
-                        };
-                        cells.push(s);
-                    }
-                    let row_refs: Vec<&str> = cells.iter().map(|s| s.as_str()).collect();
-                    table.add_row(row_refs);
-                }
-                println!("{table}");
+                handle_route(&client, &origin, &destination, raw, offline, args.verbose).await?;
             }
 
-            Commands::Metar { station, raw, json } => {
-                handle_metar(&client, &station, raw, json, args.verbose).await?;
+            Commands::Metar { station, raw, json, hours } => match hours {
+                Some(h) => handle_metar_history(&client, &station, h, raw, json, offline).await?,
+                None => handle_metar(&client, &station, raw, json, args.verbose, offline).await?,
             }
 
             Commands::Taf { station, raw, json } => {
-                handle_taf(&client, &station, raw, json, args.verbose).await?;
+                handle_taf(&client, &station, raw, json, args.verbose, offline).await?;
             }
 
             Commands::Weather { station, raw, json } => {
-                handle_metar(&client, &station, raw, json, args.verbose).await?;
+                handle_metar(&client, &station, raw, json, args.verbose, offline).await?;
                 println!();
-                handle_taf(&client, &station, raw, json, args.verbose).await?;
+                handle_taf(&client, &station, raw, json, args.verbose, offline).await?;
             }
 
+            Commands::Wxboard { artcc, group, json } => {
+                let artcc = match (&artcc, &group) {
+                    (None, None) => Some("ZDC".to_string()),
+                    _ => artcc,
+                };
+                let name = group.clone().or_else(|| artcc.clone()).unwrap_or_default();
+                let list = airport_list(&cfg, &cfg_path, &[], [&artcc, &group]);
+                handle_wxboard(&client, &name, &list, json, offline).await?;
+            }
+
+            Commands::Chart {
+                action: Some(action),
+                ..
+            } => match action {
+                ChartCommand::Sync {
+                    airports,
+                    artcc,
+                    group,
+                    airac,
+                    jobs,
+                } => {
+                    let list = airport_list(&cfg, &cfg_path, &airports, [&artcc, &group]);
+                    if list.is_empty() {
+                        eprintln!("Nothing to sync: pass airports or --artcc/--group.");
+                        std::process::exit(2);
+                    }
+                    if offline {
+                        eprintln!("chart sync needs network access (offline mode is on).");
+                        std::process::exit(1);
+                    }
+                    let cycle = airac.unwrap_or_else(AiracCycle::current);
+                    handle_chart_sync(&client, provider.as_ref(), &list, cycle, jobs, args.verbose).await?;
+                }
+                ChartCommand::Search {
+                    query,
+                    airports,
+                    artcc,
+                    group,
+                    airac,
+                    limit,
+                } => {
+                    let list = airport_list(&cfg, &cfg_path, &airports, [&artcc, &group]);
+                    if list.is_empty() {
+                        eprintln!("Nothing to search: pass --airports or --artcc/--group.");
+                        std::process::exit(2);
+                    }
+                    let opts = ChartOptions {
+                        link_only: true,
+                        airac,
+                        refresh: false,
+                        offline,
+                        merge: false,
+                        auto_open: false,
+                        render: None,
+                        verbose: args.verbose,
+                    };
+                    handle_chart_search(&client, provider.as_ref(), &list, &query, limit, &opts).await?;
+                }
+                ChartCommand::List {
+                    airport,
+                    types,
+                    airac,
+                    changed,
+                    json,
+                    csv,
+                } => {
+                    let opts = ChartOptions {
+                        link_only: true,
+                        airac,
+                        refresh: false,
+                        offline,
+                        merge: false,
+                        auto_open: false,
+                        render: None,
+                        verbose: args.verbose,
+                    };
+                    let format = if json {
+                        ListFormat::Json
+                    } else if csv {
+                        ListFormat::Csv
+                    } else {
+                        ListFormat::Table
+                    };
+                    let filter = ChartListFilter { types, changed };
+                    handle_chart_list(&client, provider.as_ref(), &airport, &filter, format, &opts).await?;
+                }
+                ChartCommand::Diff {
+                    target,
+                    from,
+                    to,
+                    names_only,
+                    jobs,
+                } => {
+                    let airports = group_airports(&cfg, &target)
+                        .unwrap_or_else(|| vec![target.trim().to_uppercase()]);
+                    let from = from.unwrap_or_else(AiracCycle::current);
+                    let to = to.unwrap_or_else(|| from.next());
+                    let opts = ChartDiffOptions {
+                        from,
+                        to,
+                        hash: !names_only,
+                        jobs,
+                        offline,
+                        verbose: args.verbose,
+                    };
+                    handle_chart_diff(&client, provider.as_ref(), &airports, &opts).await?;
+                }
+                ChartCommand::Text {
+                    airport,
+                    query,
+                    airac,
+                    refresh,
+                } => {
+                    let opts = ChartOptions {
+                        link_only: false,
+                        airac,
+                        refresh,
+                        offline,
+                        merge: false,
+                        auto_open: false,
+                        render: None,
+                        verbose: args.verbose,
+                    };
+                    handle_chart_text(&client, provider.as_ref(), &airport, &query, &opts).await?;
+                }
+                ChartCommand::Pin {
+                    airport,
+                    query,
+                    alias,
+                    airac,
+                } => {
+                    let opts = ChartOptions {
+                        link_only: true,
+                        airac,
+                        refresh: false,
+                        offline,
+                        merge: false,
+                        auto_open: false,
+                        render: None,
+                        verbose: args.verbose,
+                    };
+                    handle_chart_pin(&client, provider.as_ref(), &airport, &query, alias, &opts).await?;
+                }
+                ChartCommand::Unpin { alias } => {
+                    handle_chart_unpin(&alias)?;
+                }
+                ChartCommand::Recent { limit } => {
+                    handle_chart_recent(limit);
+                }
+            },
+
             Commands::Chart {
                 airport,
                 query,
                 link,
                 airac,
+                refresh,
+                merge,
+                render,
+                ..
             } => {
-                handle_chart(
-                    &client,
-                    &airport,
-                    &query,
-                    link,
+                let airport = airport.unwrap_or_default();
+                let opts = ChartOptions {
+                    link_only: link,
                     airac,
-                    /* auto_open = */ !args.no_open,
-                    args.verbose,
-                )
-                    .await?;
+                    refresh,
+                    offline,
+                    merge,
+                    auto_open: !args.no_open,
+                    render: render.or(cfg.settings.render),
+                    verbose: args.verbose,
+                };
+                // a lone pin alias or recent-list index reopens that chart
+                let saved = if query.is_empty() {
+                    ChartHistory::load().find(&airport).cloned()
+                } else {
+                    None
+                };
+                match saved {
+                    Some(entry) => handle_chart_reopen(&client, provider.as_ref(), &entry, &opts).await?,
+                    None => handle_chart(&client, provider.as_ref(), &airport, &query, &opts).await?,
+                }
+            }
+
+            Commands::Decode { text, json } => {
+                handle_decode(&text, json)?;
+            }
+
+            Commands::Winds { airport, cifp } => {
+                let path = cifp_path(&cifp, &cfg);
+                handle_winds(&client, &path, &airport, offline).await?;
+            }
+
+            Commands::Config { airport, cifp } => {
+                let path = cifp_path(&cifp, &cfg);
+                handle_config(&client, &path, &airport, offline).await?;
+            }
+
+            Commands::Atis { airport, letter, cifp } => {
+                let path = cifp_path(&cifp, &cfg);
+                handle_atis(&client, &cfg, &path, &airport, letter, offline).await?;
+            }
+
+            Commands::Procedure {
+                airport,
+                name,
+                transition,
+                cifp,
+            } => {
+                let path = cifp_path(&cifp, &cfg);
+                handle_procedure(&path, &airport, &name, transition.as_deref())?;
+            }
+
+            Commands::Airac { cycle, count } => {
+                handle_airac(cycle, count);
             }
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn airac_ident_round_trips() {
+        for (ident, effective) in [
+            ("2001", date(2020, 1, 2)),
+            ("2014", date(2020, 12, 31)),
+            ("2401", date(2024, 1, 25)),
+            ("2513", date(2025, 12, 25)),
+            ("2601", date(2026, 1, 22)),
+            ("2610", date(2026, 10, 1)),
+        ] {
+            let cycle = AiracCycle::from_ident(ident).unwrap();
+            assert_eq!(cycle.effective, effective, "{ident}");
+            assert_eq!(cycle.ident(), ident);
+        }
+    }
+
+    #[test]
+    fn airac_from_ident_rejects_bad_cycles() {
+        assert!(AiracCycle::from_ident("2600").is_err());
+        assert!(AiracCycle::from_ident("2514").is_err());
+        // 2020 had 14 cycles
+        assert!(AiracCycle::from_ident("2015").is_err());
+        assert!(AiracCycle::from_ident("26O1").is_err());
+        assert!(AiracCycle::from_ident("26010").is_err());
+    }
+
+    #[test]
+    fn airac_rolls_over_on_the_effective_date() {
+        let before = AiracCycle::containing(date(2026, 1, 21));
+        let on = AiracCycle::containing(date(2026, 1, 22));
+        assert_eq!(before.ident(), "2513");
+        assert_eq!(on.ident(), "2601");
+        assert_eq!(before.next(), on);
+        assert_eq!(before.expires(), date(2026, 1, 21));
+        // dates before the epoch still land on a cycle boundary
+        assert_eq!(AiracCycle::containing(date(2019, 12, 31)).effective, date(2019, 12, 5));
+    }
+
+    fn chart(name: &str, code: &str, pdf: &str) -> ChartInfo {
+        ChartInfo {
+            chart_name: name.to_string(),
+            chart_code: code.to_string(),
+            pdf_path: format!("https://example.test/2610/{pdf}"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn chart_diff_pairs_renames_and_amendments() {
+        let from = [
+            chart("JCOBY FOUR (RNAV)", "DP", "00294JCOBY.PDF"),
+            chart("ILS OR LOC RWY 19C", "IAP", "00294IL19C.PDF"),
+            chart("RNAV (GPS) RWY 30", "IAP", "00294R30.PDF"),
+            chart("HOT SPOT", "HOT", "00294HOT.PDF"),
+        ];
+        let to = [
+            // same PDF under a new title: a rename, not a remove + add
+            chart("JCOBY FIVE (RNAV)", "DP", "00294JCOBY.PDF"),
+            chart("ILS OR LOC RWY 19C", "IAP", "00294IL19C.PDF"),
+            chart("RNAV (GPS) RWY 30", "DP", "00294R30.PDF"),
+            chart("AIRPORT DIAGRAM", "APD", "00294AD.PDF"),
+        ];
+        // only the 19C PDF's content changed between the cycles
+        let hash_of = |c: &ChartInfo, newer: bool| {
+            let changed = newer && c.chart_name == "ILS OR LOC RWY 19C";
+            Some(format!("{}{}", c.pdf_path, if changed { "-new" } else { "" }))
+        };
+        let changes = diff_chart_lists(&from, &to, hash_of);
+        let summary: Vec<(ChartChangeKind, &str, &str)> = changes
+            .iter()
+            .map(|c| (c.kind, c.chart.chart_name.as_str(), c.detail.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (ChartChangeKind::Added, "AIRPORT DIAGRAM", ""),
+                (ChartChangeKind::Removed, "HOT SPOT", ""),
+                (ChartChangeKind::Renamed, "JCOBY FIVE (RNAV)", "was JCOBY FOUR (RNAV)"),
+                (ChartChangeKind::Amended, "ILS OR LOC RWY 19C", "PDF changed"),
+                (ChartChangeKind::Amended, "RNAV (GPS) RWY 30", "code IAP -> DP"),
+            ]
+        );
+
+        // without hashes an unchanged list has nothing to report
+        assert!(diff_chart_lists(&from, &from, |_, _| None).is_empty());
+    }
+
+    const DTPP_SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<digital_tpp cycle="2610" from_edate="0901Z  10/01/26" to_edate="0901Z  10/29/26">
+  <state_code ID="VA" state_fullname="Virginia">
+    <city_name ID="WASHINGTON" volume="NE-2">
+      <airport_name ID="WASHINGTON DULLES INTL" military="N" apt_ident="IAD" icao_ident="KIAD" alnum="294">
+        <record>
+          <chartseq>10100</chartseq>
+          <chart_code>MIN</chart_code>
+          <chart_name>TAKEOFF MINIMUMS</chart_name>
+          <useraction></useraction>
+          <pdf_name>NE2TO.PDF</pdf_name>
+          <cn_flg>N</cn_flg>
+          <amdtnum></amdtnum>
+          <amdtdate></amdtdate>
+        </record>
+        <record>
+          <chartseq>90100</chartseq>
+          <chart_code>DP</chart_code>
+          <chart_name>JCOBY FOUR (RNAV)</chart_name>
+          <useraction>C</useraction>
+          <pdf_name>00294JCOBY.PDF</pdf_name>
+          <amdtnum>4A</amdtnum>
+          <amdtdate>10/01/26</amdtdate>
+        </record>
+        <record>
+          <chart_code>IAP</chart_code>
+          <chart_name>VOR RWY 30</chart_name>
+          <useraction>D</useraction>
+          <pdf_name>00294V30.PDF</pdf_name>
+        </record>
+      </airport_name>
+      <airport_name ID="RONALD REAGAN WASHINGTON NATIONAL" apt_ident="DCA" icao_ident="KDCA">
+        <record>
+          <chart_code>APD</chart_code>
+          <chart_name>AIRPORT DIAGRAM</chart_name>
+          <pdf_name>00443AD.PDF</pdf_name>
+        </record>
+      </airport_name>
+    </city_name>
+  </state_code>
+</digital_tpp>
+"#;
+
+    #[test]
+    fn dtpp_user_action_flags() {
+        assert_eq!(UserAction::from_flag("A"), Some(UserAction::Added));
+        assert_eq!(UserAction::from_flag(" c "), Some(UserAction::Changed));
+        assert_eq!(UserAction::from_flag("D"), Some(UserAction::Deleted));
+        assert_eq!(UserAction::from_flag(""), None);
+        assert_eq!(UserAction::from_flag("X"), None);
+    }
+
+    #[test]
+    fn dtpp_metafile_reads_every_airport() {
+        let meta = parse_dtpp_metafile(DTPP_SAMPLE, "https://aeronav.faa.gov/d-tpp/2610/").unwrap();
+        assert_eq!(meta.cycle.as_deref(), Some("2610"));
+        let iad = meta.charts("KIAD");
+        let names: Vec<&str> = iad.iter().map(|c| c.chart_name.as_str()).collect();
+        assert_eq!(names, ["TAKEOFF MINIMUMS", "JCOBY FOUR (RNAV)", "VOR RWY 30"]);
+
+        let jcoby = &iad[1];
+        assert_eq!(jcoby.chart_code, "DP");
+        assert_eq!(jcoby.pdf_path, "https://aeronav.faa.gov/d-tpp/2610/00294JCOBY.PDF");
+        assert_eq!(jcoby.amendment.as_deref(), Some("4A"));
+        assert_eq!(jcoby.user_action, Some(UserAction::Changed));
+        assert_eq!(jcoby.amendment_note().as_deref(), Some("Amdt 4A (10/01/26), changed this cycle"));
+        assert_eq!(iad[0].amendment, None);
+        assert_eq!(iad[0].amendment_note(), None);
+        // deleted records stay in the listing for `--changed`, but can't be opened
+        assert!(iad[2].is_deleted());
+        assert_eq!(iad[2].amendment_note().as_deref(), Some("deleted this cycle"));
+        let live: Vec<String> = live_charts(iad).into_iter().map(|c| c.chart_name).collect();
+        assert_eq!(live, ["TAKEOFF MINIMUMS", "JCOBY FOUR (RNAV)"]);
+
+        // FAA identifiers work too, and each airport only gets its own records
+        let dca = meta.charts("DCA");
+        assert_eq!(dca.len(), 1);
+        assert_eq!(dca[0].pdf_path, "https://aeronav.faa.gov/d-tpp/2610/00443AD.PDF");
+        assert_eq!(meta.charts("KDCA").len(), 1);
+        assert!(meta.charts("BWI").is_empty());
+        assert!(parse_dtpp_metafile("<digital_tpp", "").is_err());
+    }
+
+    #[test]
+    fn chart_diff_treats_deleted_records_as_removed() {
+        let from = [chart("VOR RWY 30", "IAP", "00294V30.PDF")];
+        let mut gone = from[0].clone();
+        gone.user_action = Some(UserAction::Deleted);
+        let changes = diff_chart_lists(&from, &live_charts(vec![gone]), |_, _| None);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChartChangeKind::Removed);
+    }
+
+    #[test]
+    fn chart_diff_reports_amendment_numbers() {
+        let mut from = chart("ILS OR LOC RWY 19C", "IAP", "00294IL19C.PDF");
+        from.amendment = Some("3".to_string());
+        let mut to = from.clone();
+        to.amendment = Some("4".to_string());
+        let changes = diff_chart_lists(&[from], &[to], |_, _| None);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].detail, "Amdt 3 -> 4");
+    }
+
+    #[test]
+    fn alias_keys_ignore_case_spacing_and_a_leading_the() {
+        assert_eq!(alias_key("  The   Dulles  Visual "), "dulles visual");
+        assert_eq!(alias_key("JCOBY4"), "jcoby4");
+        assert_eq!(alias_key("the"), "the");
+    }
+
+    #[test]
+    fn alias_user_entries_override_shipped_ones() {
+        let mut table = AliasTable::default();
+        table.merge(toml::from_str(include_str!("aliases.toml")).unwrap());
+        assert_eq!(table.lookup(Some("KIAD"), "jcoby4"), Some("JCOBY FOUR (RNAV)"));
+        assert_eq!(table.lookup(Some("IAD"), "the taxi"), Some("AIRPORT DIAGRAM"));
+        assert_eq!(table.airport_name("KIAD"), Some("DULLES"));
+
+        let user: AliasFile = toml::from_str(
+            r#"
+            [names]
+            IAD = "dulles intl"
+
+            [global]
+            taxi = "airport diagram, cont.1"
+            "river" = "river visual rwy 19"
+
+            [airports.IAD]
+            jcoby4 = "jcoby five (rnav)"
+            river = "potomac visual"
+            "#,
+        )
+        .unwrap();
+        table.merge(user);
+        assert_eq!(table.lookup(Some("IAD"), "JCOBY4"), Some("JCOBY FIVE (RNAV)"));
+        assert_eq!(table.lookup(None, "taxi"), Some("AIRPORT DIAGRAM, CONT.1"));
+        assert_eq!(table.airport_name("IAD"), Some("DULLES INTL"));
+        // an airport's own alias beats a global one with the same key
+        assert_eq!(table.lookup(Some("KIAD"), "river"), Some("POTOMAC VISUAL"));
+        assert_eq!(table.lookup(Some("DCA"), "river"), Some("RIVER VISUAL RWY 19"));
+        assert_eq!(table.lookup(Some("DCA"), "jcoby4"), None);
+    }
+
+    #[test]
+    fn runway_queries_and_chart_titles() {
+        let q = |s: &str| runway_query(&s.split_whitespace().map(String::from).collect::<Vec<_>>());
+        assert_eq!(q("19C"), Some("19C".to_string()));
+        assert_eq!(q("rwy 01r"), Some("1R".to_string()));
+        assert_eq!(q("approaches 1"), Some("1".to_string()));
+        assert_eq!(q("ILS 19C"), None);
+        assert_eq!(q("1 19"), None);
+        assert_eq!(q("37"), None);
+        assert_eq!(q("approaches"), None);
+
+        assert_eq!(chart_runways("ILS OR LOC RWY 01R"), ["1R"]);
+        assert_eq!(chart_runways("VOR RWYS 1/19"), ["1", "19"]);
+        assert_eq!(chart_runways("RNAV (GPS) Z RWY 19L, CONT.1"), ["19L"]);
+        assert!(chart_runways("AIRPORT DIAGRAM").is_empty());
+    }
+
+    #[test]
+    fn approaches_for_a_runway_include_parallels_only_when_unsided() {
+        let iap = |name: &str| ChartInfo {
+            chart_name: name.to_string(),
+            chart_code: "IAP".to_string(),
+            pdf_path: String::new(),
+            ..Default::default()
+        };
+        let charts = [
+            iap("RNAV (GPS) Z RWY 01R"),
+            iap("ILS OR LOC RWY 01R"),
+            iap("ILS OR LOC RWY 01R, CONT.1"),
+            iap("ILS OR LOC RWY 01L"),
+            iap("ILS OR LOC RWY 19C"),
+            ChartInfo {
+                chart_name: "RIVER VISUAL RWY 19".to_string(),
+                ..Default::default()
+            },
+            ChartInfo {
+                chart_name: "JCOBY FOUR (RNAV) RWY 01R".to_string(),
+                chart_code: "DP".to_string(),
+                ..Default::default()
+            },
+        ];
+        let names = |rwy: &str| -> Vec<String> {
+            approaches_for_runway(&charts, rwy).into_iter().map(|c| c.chart_name).collect()
+        };
+        assert_eq!(names("1R"), ["ILS OR LOC RWY 01R", "RNAV (GPS) Z RWY 01R"]);
+        assert_eq!(names("1"), ["ILS OR LOC RWY 01L", "ILS OR LOC RWY 01R", "RNAV (GPS) Z RWY 01R"]);
+        assert_eq!(names("19"), ["ILS OR LOC RWY 19C", "RIVER VISUAL RWY 19"]);
+        assert!(names("30").is_empty());
+    }
+
+    fn words(q: &str) -> Vec<String> {
+        q.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn chart_history_finds_recent_by_index_and_pins_by_alias() {
+        let cycle = AiracCycle::containing(date(2026, 10, 1));
+        let mut history = ChartHistory::default();
+        history.record("iad", &words("jcoby4"), "JCOBY FOUR (RNAV)", &cycle, &["a.pdf".to_string()]);
+        history.record("DCA", &words("diagram"), "AIRPORT DIAGRAM", &cycle, &["b.pdf".to_string()]);
+        history.pins.push(PinnedChart {
+            alias: "j4".to_string(),
+            chart: ChartRef::new("IAD", &words("jcoby4"), "JCOBY FOUR (RNAV)"),
+        });
+
+        // the newest lookup is #1; 0 and out-of-range numbers find nothing
+        assert_eq!(history.find("1").map(|r| r.airport.as_str()), Some("DCA"));
+        assert_eq!(history.find("2").map(|r| r.airport.as_str()), Some("IAD"));
+        assert!(history.find("0").is_none());
+        assert!(history.find("3").is_none());
+        assert_eq!(history.find(" J4 ").map(|r| r.chart_name.as_str()), Some("JCOBY FOUR (RNAV)"));
+        assert!(history.find("j5").is_none());
+    }
+
+    #[test]
+    fn chart_history_record_dedups_and_keeps_two_cycles() {
+        let first = AiracCycle::containing(date(2026, 10, 1));
+        let second = first.next();
+        let third = second.next();
+        let mut history = ChartHistory::default();
+        history.pins.push(PinnedChart {
+            alias: "j4".to_string(),
+            chart: ChartRef::new("IAD", &words("jcoby4"), "JCOBY FOUR (RNAV)"),
+        });
+        history.record("IAD", &words("jcoby4"), "JCOBY FOUR (RNAV)", &first, &["p1.pdf".to_string()]);
+        history.record("DCA", &words("diagram"), "AIRPORT DIAGRAM", &first, &["ad.pdf".to_string()]);
+        history.record("iad", &[], "JCOBY FOUR (RNAV)", &second, &["p2.pdf".to_string()]);
+        history.record("IAD", &words("jcoby 4"), "JCOBY FOUR (RNAV)", &third, &["p3.pdf".to_string()]);
+
+        // one entry per chart, most recent first, with the latest query text
+        assert_eq!(history.recent.len(), 2);
+        let jcoby = &history.recent[0];
+        assert_eq!(jcoby.query, "jcoby 4");
+        // only the two newest cycles' PDFs survive
+        let cycles: Vec<&String> = jcoby.pdfs.keys().collect();
+        assert_eq!(cycles, [&second.ident(), &third.ident()]);
+        // the pin for the same chart picks up the resolved PDFs too
+        assert_eq!(history.pins[0].chart.pdfs.get(&third.ident()), Some(&vec!["p3.pdf".to_string()]));
+
+        for n in 0..RECENT_CHARTS + 5 {
+            history.record("BWI", &[], &format!("CHART {n}"), &first, &[]);
+        }
+        assert_eq!(history.recent.len(), RECENT_CHARTS);
+        assert_eq!(history.recent[0].chart_name, format!("CHART {}", RECENT_CHARTS + 4));
+    }
+
+    /// A 132-column ARINC 424 record with `fields` placed at their 1-based start columns.
+    fn arinc_record(fields: &[(usize, &str)]) -> String {
+        let mut line = vec![b' '; 132];
+        for (col, value) in fields {
+            line[col - 1..col - 1 + value.len()].copy_from_slice(value.as_bytes());
+        }
+        String::from_utf8(line).unwrap()
+    }
+
+    /// A KIAD SID leg record: procedure, route type, transition, sequence and fix, plus `extra`.
+    fn sid_leg(
+        ident: &str,
+        route_type: &str,
+        transition: &str,
+        seq: &str,
+        fix: &str,
+        extra: &[(usize, &str)],
+    ) -> String {
+        let mut fields = vec![
+            (1, "SUSAP KIADK6D"),
+            (14, ident),
+            (20, route_type),
+            (21, transition),
+            (27, seq),
+            (30, fix),
+            (39, "0"),
+            (129, "2610"),
+        ];
+        fields.extend_from_slice(extra);
+        arinc_record(&fields)
+    }
+
+    #[test]
+    fn cifp_procedures_read_fixed_columns() {
+        let text = [
+            sid_leg("JCOBY4", "4", "RW19C", "010", "", &[(48, "VA"), (71, "1950"), (83, "+"), (85, "01000")]),
+            sid_leg("JCOBY4", "4", "RW19C", "020", "BUNZZ", &[(48, "DF")]),
+            // continuation record for the same leg: ignored
+            sid_leg("JCOBY4", "4", "RW19C", "020", "BUNZZ", &[(39, "2"), (48, "DF")]),
+            sid_leg(
+                "JCOBY4",
+                "5",
+                "ALL",
+                "020",
+                "JCOBY",
+                &[(48, "TF"), (83, "B"), (85, "12000"), (90, "10000"), (100, "250"), (118, "-")],
+            ),
+            // another airport
+            arinc_record(&[(1, "SUSAP KDCAK6ECAVLR4"), (20, "4"), (21, "JAXSN"), (30, "JAXSN"), (48, "IF")]),
+        ]
+        .join("\n");
+
+        let procs = parse_cifp_procedures(&text, "iad");
+        assert_eq!(procs.len(), 1);
+        let p = &procs[0];
+        assert_eq!(p.ident, "JCOBY4");
+        assert_eq!(p.kind, ProcedureKind::Sid);
+        assert_eq!(p.cycle, "2610");
+        assert_eq!(p.legs.len(), 3);
+
+        let first = &p.legs[0];
+        assert_eq!(first.transition, "RW19C");
+        assert_eq!(first.route_type, '4');
+        assert_eq!(first.fix, "");
+        assert_eq!(first.path, "VA");
+        assert_eq!(first.course, Some(195.0));
+        assert_eq!(first.altitude, "+01000");
+
+        let common = &p.legs[2];
+        assert_eq!(common.fix, "JCOBY");
+        assert_eq!(common.altitude, "10000-12000");
+        assert_eq!(common.speed, "-250");
+        assert_eq!(p.transitions(), vec![("RW19C".to_string(), "runway"), ("ALL".to_string(), "common")]);
+
+        let star = parse_cifp_procedures(&text, "KDCA");
+        assert_eq!(star[0].ident, "CAVLR4");
+        assert_eq!(star[0].kind, ProcedureKind::Star);
+    }
+
+    #[test]
+    fn metar_decodes_rvr_variable_wind_and_remarks() {
+        let m = parse_metar(
+            "SPECI KIAD 161852Z AUTO 27015G25KT 240V300 1 1/2SM R01R/2400V4000FT/U R19C/M0600FT -SN BR \
+             BKN008 OVC015 M02/M05 A2992 RMK AO2 PK WND 28032/1830 SLP133 T10221050=",
+        )
+        .unwrap();
+        assert_eq!(m.kind, "SPECI");
+        assert_eq!(m.station, "KIAD");
+        assert_eq!(m.time, Some(ReportTime { day: 16, hour: 18, minute: 52 }));
+        assert!(m.auto);
+        assert!(m.unparsed.is_empty(), "{:?}", m.unparsed);
+
+        let wind = m.conditions.wind.as_ref().unwrap();
+        assert_eq!((wind.direction, wind.speed, wind.gust), (Some(270), 15, Some(25)));
+        assert_eq!((wind.variable_from, wind.variable_to), (Some(240), Some(300)));
+        assert_eq!(m.conditions.visibility.unwrap().statute_miles, 1.5);
+
+        assert_eq!(m.rvr.len(), 2);
+        assert_eq!(m.rvr[0].runway, "01R");
+        assert_eq!((m.rvr[0].feet, m.rvr[0].variable_to, m.rvr[0].trend), (2400, Some(4000), Some('U')));
+        assert_eq!((m.rvr[1].runway.as_str(), m.rvr[1].feet, m.rvr[1].qualifier), ("19C", 600, Some('M')));
+
+        assert_eq!(m.conditions.weather, ["-SN", "BR"]);
+        assert_eq!(m.conditions.ceiling_ft(), Some(800));
+        assert_eq!(m.conditions.flight_category(), Some(FlightCategory::Ifr));
+
+        assert_eq!((m.temperature_c, m.dewpoint_c), (Some(-2.0), Some(-5.0)));
+        assert_eq!(m.altimeter_inhg, Some(29.92));
+        assert_eq!(m.remarks.station_type.as_deref(), Some("AO2"));
+        assert_eq!(m.remarks.sea_level_pressure_hpa, Some(1013.3));
+        assert_eq!((m.temperature(), m.dewpoint()), (Some(-2.2), Some(-5.0)));
+        let pk = m.remarks.peak_wind.as_ref().unwrap();
+        assert_eq!((pk.direction, pk.speed, pk.hour, pk.minute), (280, 32, Some(18), 30));
+    }
+
+    #[test]
+    fn metar_decodes_low_pressure_and_metric_groups() {
+        let m = parse_metar("EGLL 161850Z VRB02KT 0800 R27L/P1500 FG VV002 M01/M01 Q0998 RMK SLP875").unwrap();
+        let wind = m.conditions.wind.as_ref().unwrap();
+        assert_eq!(wind.direction, None);
+        assert_eq!(m.conditions.visibility.unwrap().statute_miles, 0.5);
+        assert_eq!(m.rvr[0].qualifier, Some('P'));
+        assert_eq!(m.conditions.flight_category(), Some(FlightCategory::Lifr));
+        assert_eq!(m.temperature(), Some(-1.0));
+        // SLP is tenths of a hectopascal with the leading 9 or 10 dropped
+        assert_eq!(m.remarks.sea_level_pressure_hpa, Some(987.5));
+        assert!(parse_metar("KI 161850Z 00000KT").is_err());
+    }
+
+    #[test]
+    fn taf_groups_split_before_change_markers() {
+        let groups = split_taf_groups(
+            "19010KT P6SM SCT050 FM170000 22008KT 6SM PROB30 TEMPO 1714/1718 1SM BR BECMG 1710/1712 VRB03KT \
+             TEMPO 1720/1724 3SM",
+        );
+        assert_eq!(
+            groups,
+            [
+                "19010KT P6SM SCT050",
+                "FM170000 22008KT 6SM",
+                "PROB30 TEMPO 1714/1718 1SM BR",
+                "BECMG 1710/1712 VRB03KT",
+                "TEMPO 1720/1724 3SM",
+            ]
+        );
+    }
+
+    #[test]
+    fn taf_decodes_change_groups() {
+        let taf = parse_taf(
+            "TAF AMD KIAD 161730Z 1618/1724 19010KT P6SM SCT050 \
+             FM170000 22008KT 6SM -SHRA BKN030 WS020/24045KT \
+             TEMPO 1702/1706 3SM TSRA OVC015CB \
+             BECMG 1710/1712 VRB03KT \
+             PROB30 1714/1718 1SM BR OVC008 \
+             PROB40 TEMPO 1720/1724 2SM -RA=",
+        )
+        .unwrap();
+        assert_eq!(taf.station, "KIAD");
+        assert!(taf.amended);
+        assert_eq!(taf.issued, Some(ReportTime { day: 16, hour: 17, minute: 30 }));
+        assert_eq!(taf.valid_from, Some(ReportTime { day: 16, hour: 18, minute: 0 }));
+        assert_eq!(taf.valid_to, Some(ReportTime { day: 17, hour: 24, minute: 0 }));
+
+        let labels: Vec<String> = taf.groups.iter().map(|g| g.label()).collect();
+        assert_eq!(labels, ["BASE", "FM", "TEMPO", "BECMG", "PROB30", "PROB40 TEMPO"]);
+        for g in taf.groups.iter() {
+            assert!(g.unparsed.is_empty(), "{}: {:?}", g.raw, g.unparsed);
+        }
+
+        let base = &taf.groups[0];
+        assert_eq!(base.raw, "19010KT P6SM SCT050");
+        assert_eq!(base.conditions.flight_category(), Some(FlightCategory::Vfr));
+
+        let fm = &taf.groups[1];
+        assert_eq!(fm.change, TafChange::From);
+        assert_eq!(fm.from, Some(ReportTime { day: 17, hour: 0, minute: 0 }));
+        assert_eq!(fm.wind_shear.as_deref(), Some("WS020/24045KT"));
+        assert_eq!(fm.conditions.weather, ["-SHRA"]);
+
+        let tempo = &taf.groups[2];
+        assert_eq!(tempo.from, Some(ReportTime { day: 17, hour: 2, minute: 0 }));
+        assert_eq!(tempo.to, Some(ReportTime { day: 17, hour: 6, minute: 0 }));
+        assert_eq!(tempo.conditions.sky[0].cloud.as_deref(), Some("CB"));
+        assert!(!tempo.is_prevailing());
+
+        assert_eq!(taf.groups[3].conditions.wind.as_ref().unwrap().direction, None);
+        assert_eq!((taf.groups[4].change, taf.groups[4].probability), (TafChange::Prob, Some(30)));
+        assert_eq!(taf.groups[4].conditions.flight_category(), Some(FlightCategory::Ifr));
+        assert_eq!((taf.groups[5].change, taf.groups[5].probability), (TafChange::Tempo, Some(40)));
+    }
+
+    #[test]
+    fn cifp_runways_read_fixed_columns() {
+        let text = [
+            arinc_record(&[(1, "SUSAP KIADK6AIAD"), (22, "0"), (52, "W0110")]),
+            arinc_record(&[(1, "SUSAP KIADK6GRW01C"), (22, "0"), (23, "11500"), (28, "0010")]),
+            arinc_record(&[(1, "SUSAP KIADK6GRW19C"), (22, "0"), (23, "11500"), (28, "1910")]),
+            // continuation record: ignored
+            arinc_record(&[(1, "SUSAP KIADK6GRW19L"), (22, "2"), (23, "09400"), (28, "1910")]),
+            // true bearing: skipped
+            arinc_record(&[(1, "SUSAP KIADK6GRW30"), (22, "0"), (23, "10500"), (28, "293T")]),
+            arinc_record(&[(1, "SUSAP KDCAK6GRW01"), (22, "0"), (23, "07169"), (28, "0040")]),
+        ]
+        .join("\n");
+
+        let apt = parse_cifp_runways(&text, "IAD");
+        assert_eq!(apt.magnetic_variation, -11.0);
+        let idents: Vec<&str> = apt.runways.iter().map(|r| r.ident.as_str()).collect();
+        assert_eq!(idents, ["01C", "19C"]);
+        assert_eq!(apt.runways[0].length_ft, Some(11500));
+        assert_eq!(apt.runways[0].magnetic_bearing, 1.0);
+        assert_eq!(apt.runways[1].magnetic_bearing, 191.0);
+        assert_eq!(apt.runways[1].number(), "19");
+        assert!(parse_cifp_runways(&text, "BWI").runways.is_empty());
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.05, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn wind_components_signs() {
+        // straight down the runway, straight behind, and from either side
+        let (h, x) = wind_components(190.0, 10.0, 190.0);
+        assert_near(h, 10.0);
+        assert_near(x, 0.0);
+        let (h, _) = wind_components(10.0, 10.0, 190.0);
+        assert_near(h, -10.0);
+        let (h, x) = wind_components(280.0, 10.0, 190.0);
+        assert_near(h, 0.0);
+        assert_near(x, 10.0);
+        let (_, x) = wind_components(100.0, 10.0, 190.0);
+        assert_near(x, -10.0);
+        // across north: 350 on a runway heading 010 is 20 degrees off the nose, from the left
+        let (h, x) = wind_components(350.0, 20.0, 10.0);
+        assert_near(h, 20.0 * 20f64.to_radians().cos());
+        assert_near(x, -20.0 * 20f64.to_radians().sin());
+
+        assert_eq!(format_headwind(-4.6), "5 tail");
+        assert_eq!(format_headwind(0.3), "0");
+        assert_eq!(format_crosswind(7.5), "8 R");
+        assert_eq!(format_crosswind(-3.0), "3 L");
+    }
+
+    #[test]
+    fn runway_bearings_convert_magnetic_to_true() {
+        let rwy = |ident: &str, magnetic_bearing: f64| Runway {
+            ident: ident.to_string(),
+            length_ft: None,
+            magnetic_bearing,
+        };
+        // IAD: 11 degrees west, so 19C (191 magnetic) points due south
+        let west = AirportRunways {
+            magnetic_variation: -11.0,
+            runways: vec![rwy("19C", 191.0), rwy("01C", 5.0)],
+        };
+        assert_near(west.true_bearing(&west.runways[0]), 180.0);
+        assert_near(west.true_bearing(&west.runways[1]), 354.0);
+        let east = AirportRunways {
+            magnetic_variation: 13.5,
+            runways: vec![rwy("34", 350.0)],
+        };
+        assert_near(east.true_bearing(&east.runways[0]), 3.5);
+
+        // a METAR wind (true) of 180 is all headwind on IAD 19C, not 11 degrees off
+        let (h, x) = wind_components(180.0, 15.0, west.true_bearing(&west.runways[0]));
+        assert_near(h, 15.0);
+        assert_near(x, 0.0);
+    }
+
+    /// IAD-style runways: 11 degrees west, so 19C points due south and 30 at 300 true.
+    fn flow_airport() -> AirportRunways {
+        let rwy = |ident: &str, magnetic_bearing: f64| Runway {
+            ident: ident.to_string(),
+            length_ft: None,
+            magnetic_bearing,
+        };
+        AirportRunways {
+            magnetic_variation: -11.0,
+            runways: vec![rwy("01C", 11.0), rwy("19C", 191.0), rwy("30", 311.0)],
+        }
+    }
+
+    fn flow_rules_sample() -> FlowRules {
+        toml::from_str(
+            r#"
+            preferred = "south"
+
+            [[flows]]
+            name = "south"
+            arrivals = ["19C"]
+            departures = ["19C"]
+
+            [[flows]]
+            name = "north"
+            arrivals = ["1C"]
+            departures = ["1C"]
+
+            [[flows]]
+            name = "west"
+            arrivals = ["30"]
+            departures = ["30"]
+            max_crosswind = 10
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn flow_choice(wind_from: f64, speed: f64, gust: f64) -> (String, String) {
+        let rules = flow_rules_sample();
+        let apt = flow_airport();
+        let checks: Vec<FlowCheck> =
+            rules.flows.iter().map(|f| check_flow(f, &rules, &apt, wind_from, speed, gust)).collect();
+        let (i, reason) = recommend_flow(&rules, Some(&checks));
+        (rules.flows[i].name.clone(), reason)
+    }
+
+    #[test]
+    fn flow_limits_come_from_the_airport_or_the_flow() {
+        let rules = flow_rules_sample();
+        assert_eq!((rules.max_tailwind, rules.max_crosswind), (5.0, 20.0));
+        let apt = flow_airport();
+        let south = check_flow(&rules.flows[0], &rules, &apt, 360.0, 12.0, 18.0);
+        assert_eq!(south.exceeds.as_deref(), Some("tailwind 18 kt > 5 kt"));
+        assert_eq!(south.arrival_headwind.round(), -12.0);
+        let north = check_flow(&rules.flows[1], &rules, &apt, 360.0, 12.0, 18.0);
+        assert_eq!(north.exceeds, None);
+        assert_eq!((north.tailwind, north.arrival_headwind.round()), (0.0, 12.0));
+        // west carries its own 10 kt crosswind limit
+        let west = check_flow(&rules.flows[2], &rules, &apt, 360.0, 12.0, 18.0);
+        assert_eq!(west.exceeds.as_deref(), Some("crosswind 16 kt > 10 kt"));
+    }
+
+    #[test]
+    fn flow_recommendation_rules() {
+        assert_eq!(
+            flow_choice(180.0, 10.0, 10.0),
+            ("south".to_string(), "preferred south flow is within limits (tailwind 0 kt, crosswind 0 kt)".to_string())
+        );
+        assert_eq!(
+            flow_choice(360.0, 12.0, 18.0),
+            (
+                "north".to_string(),
+                "preferred south flow exceeds limits (tailwind 18 kt > 5 kt); north flow has the most arrival \
+                 headwind (12 kt) within limits"
+                    .to_string()
+            )
+        );
+        let (name, reason) = flow_choice(90.0, 30.0, 30.0);
+        assert_eq!(name, "south");
+        assert!(reason.ends_with("no flow is within limits; south flow has the least tailwind (0 kt)"), "{reason}");
+
+        let rules = flow_rules_sample();
+        assert_eq!(recommend_flow(&rules, None).0, 0);
+        let unordered = FlowRules { preferred: Some("north".to_string()), ..flow_rules_sample() };
+        assert_eq!(recommend_flow(&unordered, None), (1, "calm or variable wind: preferred north flow".to_string()));
+    }
+
+    #[test]
+    fn atis_letter_advances_and_wraps() {
+        let mut state = AtisState::default();
+        assert_eq!(state.letter_for("IAD", "KIAD 161752Z", None), 'A');
+        assert_eq!(state.letter_for("IAD", "KIAD 161752Z", None), 'A');
+        assert_eq!(state.letter_for("IAD", "KIAD 161852Z", None), 'B');
+        assert_eq!(state.letter_for("IAD", "KIAD 161852Z", Some('Z')), 'Z');
+        assert_eq!(state.letter_for("IAD", "KIAD 161952Z", None), 'A');
+        // each airport keeps its own letter
+        assert_eq!(state.letter_for("DCA", "KDCA 161952Z", None), 'A');
+        assert_eq!(state.airports["IAD"].metar, "KIAD 161952Z");
+    }
+
+    #[test]
+    fn atis_visibility_is_spoken_in_fractions() {
+        let vis = |statute_miles: f64, less_than: bool, greater_than: bool| Visibility {
+            statute_miles,
+            less_than,
+            greater_than,
+        };
+        assert_eq!(spoken_visibility(&vis(10.0, false, false)), "10");
+        assert_eq!(spoken_visibility(&vis(1.5, false, false)), "1 1/2");
+        assert_eq!(spoken_visibility(&vis(0.75, false, false)), "3/4");
+        assert_eq!(spoken_visibility(&vis(2.0 + 5.0 / 8.0, false, false)), "2 5/8");
+        assert_eq!(spoken_visibility(&vis(0.25, true, false)), "LESS THAN 1/4");
+        assert_eq!(spoken_visibility(&vis(6.0, false, true)), "MORE THAN 6");
+        // metric conversions land between eighths and round to the nearest
+        assert_eq!(spoken_visibility(&vis(1.86, false, false)), "1 7/8");
+        assert_eq!(spoken_visibility(&vis(2.97, false, false)), "3");
+    }
+
+    #[test]
+    fn atis_letters_parse_and_spell_safely() {
+        assert_eq!(parse_atis_letter("b"), Ok('B'));
+        assert_eq!(parse_atis_letter("Bravo"), Ok('B'));
+        assert_eq!(parse_atis_letter("ALFA"), Ok('A'));
+        assert_eq!(parse_atis_letter("alpha"), Ok('A'));
+        assert_eq!(parse_atis_letter("Juliet"), Ok('J'));
+        assert!(parse_atis_letter("BOGUS").is_err());
+        assert!(parse_atis_letter("7").is_err());
+        assert_eq!(phonetic('Z'), "ZULU");
+        assert_eq!(phonetic('c'), "CHARLIE");
+        assert_eq!(phonetic('7'), "ALFA");
+    }
+
+    /// Two reports `hours` apart with the given wind and altimeter groups.
+    fn trend_of(hours: i64, wind0: &str, alt0: &str, wind1: &str, alt1: &str) -> Vec<String> {
+        let obs = |t: i64, wind: &str, alt: &str| {
+            let raw = format!("KIAD 16{:02}52Z {wind} 10SM FEW050 18/06 {alt}", 12 + t);
+            (t * 3600, parse_metar(&raw).unwrap())
+        };
+        metar_trend(&[obs(0, wind0, alt0), obs(hours, wind1, alt1)])
+    }
+
+    fn trend_line<'a>(lines: &'a [String], label: &str) -> &'a str {
+        lines
+            .iter()
+            .find_map(|l| l.trim().strip_prefix(label))
+            .map(str::trim)
+            .unwrap_or_else(|| panic!("no {label} line in {lines:?}"))
+    }
+
+    #[test]
+    fn metar_trend_wind_veers_and_backs() {
+        let wind = |w0: &str, w1: &str| {
+            let lines = trend_of(2, w0, "A3000", w1, "A3000");
+            trend_line(&lines, "Wind:").to_string()
+        };
+        assert_eq!(wind("18010KT", "22010KT"), "veering 180° -> 220°");
+        assert_eq!(wind("22010KT", "18010KT"), "backing 220° -> 180°");
+        // through north, the short way round
+        assert_eq!(wind("35010KT", "02010KT"), "veering 350° -> 020°");
+        assert_eq!(wind("02010KT", "35010KT"), "backing 020° -> 350°");
+        assert_eq!(wind("18010KT", "19510KT"), "steady from 195°");
+        assert_eq!(
+            wind("18010KT", "18015G25KT"),
+            "steady from 180°, increasing 10 -> 15 kt, gusts to 25 now reported"
+        );
+        assert_eq!(wind("18014KT", "18010KT"), "steady from 180°");
+        assert_eq!(wind("18015KT", "18010KT"), "steady from 180°, decreasing 15 -> 10 kt");
+    }
+
+    #[test]
+    fn metar_trend_pressure_thresholds() {
+        let pressure = |hours: i64, a0: &str, a1: &str| {
+            let lines = trend_of(hours, "18010KT", a0, "18010KT", a1);
+            trend_line(&lines, "Pressure:").split(',').next().unwrap().to_string()
+        };
+        assert_eq!(pressure(1, "A2992", "A2993"), "steady");
+        assert_eq!(pressure(3, "A2992", "A2998"), "rising");
+        assert_eq!(pressure(1, "A2992", "A3000"), "rising rapidly");
+        assert_eq!(pressure(3, "A2992", "A2985"), "falling");
+        assert_eq!(pressure(1, "A2992", "A2980"), "falling rapidly");
+        let lines = trend_of(4, "18010KT", "A2992", "18010KT", "A2980");
+        assert_eq!(trend_line(&lines, "Pressure:"), "falling, 29.92 -> 29.80 inHg (-0.12)");
+        assert_eq!(lines[0], "Trend 1252Z -> 1652Z (4.0 h)");
+    }
+
+    #[test]
+    fn taf_metric_visibility_needs_wind_or_a_period() {
+        let taf = parse_taf("TAF EGLL 161100Z 1612/1718 24010KT 9999 SCT030 TEMPO 1612/1616 3000 BR").unwrap();
+        assert_eq!(taf.groups[1].conditions.visibility.unwrap().statute_miles, 1.86);
+        assert_eq!(taf.groups[1].conditions.weather, ["BR"]);
+
+        // a stray 4-digit group ahead of the wind is not visibility
+        let m = parse_metar("KXYZ 161153Z 1234 18010KT 9999 FEW030 20/10 Q1015").unwrap();
+        assert!(m.conditions.visibility.unwrap().greater_than);
+        assert_eq!(m.unparsed, ["1234"]);
+    }
+
+    #[test]
+    fn flow_without_cifp_runways_is_never_chosen() {
+        let mut rules = flow_rules_sample();
+        rules.flows.push(RunwayFlow {
+            name: "east".to_string(),
+            arrivals: vec!["12".to_string()],
+            departures: vec!["12".to_string()],
+            ..rules.flows[0].clone()
+        });
+        rules.preferred = Some("east".to_string());
+        let apt = flow_airport();
+        let checks: Vec<FlowCheck> = rules.flows.iter().map(|f| check_flow(f, &rules, &apt, 180.0, 10.0, 10.0)).collect();
+        assert!(!checks[3].checked);
+        assert!(checks[..3].iter().all(|c| c.checked));
+        let (i, reason) = recommend_flow(&rules, Some(&checks));
+        assert_eq!(rules.flows[i].name, "south");
+        assert!(reason.starts_with("preferred east flow could not be checked (no runways in CIFP); "), "{reason}");
+
+        // a tailwind everywhere still falls back to a checked flow
+        let checks: Vec<FlowCheck> = rules.flows.iter().map(|f| check_flow(f, &rules, &apt, 90.0, 30.0, 30.0)).collect();
+        assert_ne!(rules.flows[recommend_flow(&rules, Some(&checks)).0].name, "east");
+    }
+
+    /// Empty directory under the system temp dir, unique to this test process.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zdc-test-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn expired_cycle_dirs_are_pruned() {
+        let root = scratch_dir("prune");
+        let current = AiracCycle::containing(date(2026, 10, 1));
+        for name in [current.previous().ident(), current.ident(), current.next().ident(), "notes".to_string()] {
+            fs::create_dir_all(root.join(name).join("IAD")).unwrap();
+        }
+        prune_cycle_dirs(&root, &current);
+        let mut left: Vec<String> = fs::read_dir(&root)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, [current.ident(), current.next().ident(), "notes".to_string()]);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn sync_baselines_survive_the_rollover_prune() {
+        let root = scratch_dir("sync-rollover");
+        let current = AiracCycle::containing(date(2026, 10, 1));
+        let previous = current.previous();
+        let listing = |cycle: &AiracCycle, airport: &str, names: &[&str]| {
+            let dir = airport_cycle_dir(&root, cycle, airport);
+            fs::create_dir_all(&dir).unwrap();
+            let entry = ChartCacheEntry {
+                provider: "aviationapi".to_string(),
+                cycle: cycle.ident(),
+                fetched_at: 0,
+                charts: names.iter().map(|n| chart(n, "IAP", "x.pdf")).collect(),
+            };
+            fs::write(dir.join("charts.json"), serde_json::to_string(&entry).unwrap()).unwrap();
+        };
+        listing(&previous, "IAD", &["ILS OR LOC RWY 19C"]);
+        listing(&previous, "DCA", &["RNAV (GPS) RWY 19"]);
+        listing(&current, "BWI", &["ILS RWY 10"]);
+        listing(&previous, "BWI", &["ILS RWY 28"]);
+
+        let airports = ["IAD", "DCA", "BWI"].map(String::from);
+        let baselines = sync_baselines(&root, &current, &airports);
+        // the first airport's fetch prunes the previous cycle from under the others
+        prune_cycle_dirs(&root, &current);
+        assert!(read_chart_cache(&root, &previous, "DCA").is_none());
+
+        let names: Vec<Option<String>> = baselines
+            .iter()
+            .map(|b| b.as_ref().map(|charts| charts[0].chart_name.clone()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                Some("ILS OR LOC RWY 19C".to_string()),
+                Some("RNAV (GPS) RWY 19".to_string()),
+                // a listing already stored for the synced cycle wins
+                Some("ILS RWY 10".to_string()),
+            ]
+        );
+        assert!(sync_baselines(&root, &current, &["RIC".to_string()])[0].is_none());
+        fs::remove_dir_all(&root).ok();
+    }
+}